@@ -0,0 +1,174 @@
+use crate::in_game::balls::ammo_ball::{AMMO_MASS, AMMO_RESTITUTION, AMMO_SIZE};
+use crate::in_game::balls::level_ball::LevelBall;
+use crate::in_game::levels::properties::TiledProperties;
+use crate::in_game::levels::LevelCollider;
+use crate::in_game::physics_layers::GameLayer;
+use crate::in_game::player::{muzzle, Ammo, Player, ShootingForce};
+use crate::in_game::simulation::SIMULATION_HZ;
+use crate::in_game::states::GameState;
+use crate::settings::Settings;
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+pub(super) fn aim_preview_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        draw_aim_preview.run_if(
+            in_state(GameState::Playing)
+                .and(resource_exists::<AimPreview>)
+                .and(|settings: Res<Settings>| settings.aim_preview),
+        ),
+    );
+}
+
+/// How much of the predicted ammo path is drawn while aiming, set per level
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct AimPreview {
+    /// Length of the drawn path in world units, 0 turns the preview off
+    pub length: f32,
+    /// Bounces off level geometry to follow before the path ends
+    pub bounces: u32,
+}
+
+impl Default for AimPreview {
+    fn default() -> Self {
+        Self {
+            length: 1500.0,
+            bounces: 2,
+        }
+    }
+}
+
+impl AimPreview {
+    pub fn from_properties(properties: &TiledProperties) -> Self {
+        let default = Self::default();
+        Self {
+            length: properties.get("aim_preview").unwrap_or(default.length),
+            bounces: properties.get("aim_preview_bounces").unwrap_or(default.bounces),
+        }
+    }
+}
+
+// Same rate as the fixed gameplay step, so the preview follows the integration of the real ball
+const PREVIEW_STEP: f32 = 1.0 / SIMULATION_HZ as f32;
+const MAX_PREVIEW_STEPS: usize = 1000;
+// Nudge off a surface after a bounce so the next cast doesn't hit it again right away
+const BOUNCE_SKIN: f32 = 0.5;
+const MAX_HITS_PER_CAST: u32 = 8;
+const PREVIEW_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.5);
+const BOUNCE_COLOR: Color = Color::srgb(1.0, 0.9, 0.3);
+const TARGET_COLOR: Color = Color::srgb(1.0, 0.4, 0.3);
+
+enum PreviewHit {
+    Bounce { normal: Vec2, restitution: f32 },
+    Ball,
+}
+
+fn draw_aim_preview(
+    preview: Res<AimPreview>,
+    players: Query<(&Transform, &ShootingForce, Option<&Ammo>), With<Player>>,
+    spatial_query: SpatialQuery,
+    gravity: Res<Gravity>,
+    colliders: Query<(Option<&Restitution>, Option<&CollisionLayers>, Has<Sensor>), With<LevelCollider>>,
+    level_balls: Query<(), With<LevelBall>>,
+    mut gizmos: Gizmos,
+) {
+    if preview.length <= 0.0 {
+        return;
+    }
+
+    let shape = Collider::circle(AMMO_SIZE / 2.0);
+    let filter = SpatialQueryFilter::from_mask([GameLayer::Default, GameLayer::Ball]);
+
+    // The first thing in the way of the ammo, ignoring geometry it passes through
+    let cast = |origin: Vec2, direction: Dir2, distance: f32| {
+        let config = ShapeCastConfig {
+            max_distance: distance,
+            ignore_origin_penetration: true,
+            ..default()
+        };
+        spatial_query
+            .shape_hits(&shape, origin, 0.0, direction, MAX_HITS_PER_CAST, &config, &filter)
+            .into_iter()
+            .filter_map(|hit| {
+                if level_balls.contains(hit.entity) {
+                    return Some((hit.distance, PreviewHit::Ball));
+                }
+                let (restitution, layers, sensor) = colliders.get(hit.entity).ok()?;
+                let blocks_ammo = layers.is_none_or(|layers| layers.filters.has_all(GameLayer::Ammo));
+                if sensor || !blocks_ammo {
+                    return None;
+                }
+                // Restitution of both bodies is averaged, like the physics engine does by default
+                let restitution = (AMMO_RESTITUTION + restitution.map_or(0.0, |r| r.coefficient)) / 2.0;
+                Some((
+                    hit.distance,
+                    PreviewHit::Bounce {
+                        normal: hit.normal1,
+                        restitution,
+                    },
+                ))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    };
+
+    for (transform, force, ammo) in players.iter() {
+        if ammo.is_some_and(|ammo| ammo.remaining == 0) {
+            continue;
+        }
+
+        let (mut position, direction) = muzzle(transform);
+        // The shot is an impulse, so the ammo starts with impulse / mass
+        let mut velocity = direction * force.value() / AMMO_MASS;
+        let mut points = vec![position];
+        let mut travelled = 0.0;
+        let mut bounces = 0;
+
+        for _ in 0..MAX_PREVIEW_STEPS {
+            if travelled >= preview.length {
+                break;
+            }
+
+            velocity += gravity.0 * PREVIEW_STEP;
+            let step = velocity * PREVIEW_STEP;
+            let Ok(step_direction) = Dir2::new(step) else {
+                break;
+            };
+            let distance = step.length().min(preview.length - travelled);
+
+            let Some((hit_distance, hit)) = cast(position, step_direction, distance) else {
+                position += step_direction * distance;
+                travelled += distance;
+                points.push(position);
+                continue;
+            };
+
+            position += step_direction * hit_distance;
+            travelled += hit_distance;
+            points.push(position);
+
+            match hit {
+                PreviewHit::Ball => {
+                    gizmos.circle_2d(position, AMMO_SIZE / 2.0, TARGET_COLOR);
+                    break;
+                }
+                PreviewHit::Bounce { normal, restitution } => {
+                    if bounces >= preview.bounces {
+                        break;
+                    }
+                    bounces += 1;
+                    gizmos.circle_2d(position, 4.0, BOUNCE_COLOR);
+
+                    // Flip the part of the velocity going into the surface, losing some of it
+                    let normal_speed = velocity.dot(normal);
+                    if normal_speed < 0.0 {
+                        velocity -= (1.0 + restitution) * normal_speed * normal;
+                    }
+                    position += normal * BOUNCE_SKIN;
+                }
+            }
+        }
+
+        gizmos.linestrip_2d(points, PREVIEW_COLOR);
+    }
+}
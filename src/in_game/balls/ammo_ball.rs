@@ -3,8 +3,9 @@ use crate::in_game::input::PlayerInputContext;
 use bevy::prelude::*;
 use avian2d::{math::*, prelude::*};
 use avian2d::dynamics::integrator::IntegrationSet::Velocity;
-use bevy::color::palettes::basic::GREEN;
 use bevy_enhanced_input::actions::Actions;
+use crate::in_game::physics_layers::GameLayer;
+use crate::in_game::states::InLevel;
 
 #[derive(Component)]
 pub struct AmmoBall;
@@ -15,6 +16,17 @@ pub struct SplitChain {
     pub ammo_id: u32,
 }
 
+/// Sent when a new ammo ball enters the level
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ShotFired {
+    pub ammo_id: u32,
+}
+
+// Size of the ammo sprite, the collider is a circle inscribed in it
+pub const AMMO_SIZE: f32 = 30.0;
+pub const AMMO_MASS: f32 = 32.0;
+pub const AMMO_RESTITUTION: f32 = 1.0;
+
 // Resource to generate unique IDs for ammo balls
 #[derive(Resource)]
 struct NextAmmoId(u32);
@@ -27,38 +39,33 @@ impl Default for NextAmmoId {
 
 pub(in crate::in_game) fn ammo_ball_plugin(app: &mut App) {
     app.init_resource::<NextAmmoId>()
+        .add_event::<ShotFired>()
         .add_observer(observe_ammo_ball_add);
 }
 
 fn observe_ammo_ball_add(
     trigger: Trigger<OnAdd, AmmoBall>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
     mut next_id: ResMut<NextAmmoId>,
 ) {
-    let ball_radius = 30.0;
-
     let mut entity_commands = commands.entity(trigger.target());
     entity_commands.insert((
-        Sprite {
-            image: asset_server.load("ball.png"),
-            custom_size: Some(Vec2::splat(ball_radius)),
-            color: GREEN.into(),
-            ..Default::default()
-        },
         RigidBody::Dynamic,
         CollisionEventsEnabled,
-        Mass(32.0),
-        Collider::circle(ball_radius / 2.0 as Scalar),
+        Mass(AMMO_MASS),
+        Collider::circle(AMMO_SIZE / 2.0 as Scalar),
+        CollisionLayers::new(GameLayer::Ammo, LayerMask::ALL),
         SplitChain {
             ammo_id: next_id.0,
         },
         PreviousVelocity(Vec2::ZERO),
         Restitution {
-            coefficient: 1.0,
+            coefficient: AMMO_RESTITUTION,
             ..Default::default()
         },
+        StateScoped(InLevel),
     ));
-    
+
+    commands.send_event(ShotFired { ammo_id: next_id.0 });
     next_id.0 += 1;
 }
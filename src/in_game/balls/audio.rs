@@ -3,6 +3,8 @@ use avian2d::prelude::*;
 use rand::Rng;
 use crate::in_game::balls::level_ball::{LevelBall};
 use crate::in_game::balls::ammo_ball::{SplitChain, AmmoBall};
+use crate::in_game::player::ShotRefused;
+use crate::in_game::simulation::EffectsRng;
 
 // Configuration for the collision sound
 #[derive(Resource)]
@@ -37,7 +39,7 @@ struct ActiveSoundCount(usize);
 pub(in crate::in_game) fn audio_plugin(app: &mut App) {
     app.init_resource::<CollisionSoundConfig>()
         .init_resource::<ActiveSoundCount>()
-        .add_systems(Update, (play_collision_sound, cleanup_finished_sounds));
+        .add_systems(Update, (play_collision_sound, play_refused_sound, cleanup_finished_sounds));
 }
 
 fn cleanup_finished_sounds(
@@ -61,8 +63,8 @@ fn play_collision_sound(
     split_chains: Query<&SplitChain>,
     level_balls: Query<&LevelBall>,
     mut active_count: ResMut<ActiveSoundCount>,
+    mut rng: ResMut<EffectsRng>,
 ) {
-    let mut rng = rand::rng();
 
     for CollisionStarted(entity1, entity2) in collision_events.read() {
         // Skip if we've reached the maximum number of simultaneous sounds
@@ -91,7 +93,7 @@ fn play_collision_sound(
             let pitch = base_pitch.min(config.max_pitch);
 
             // Add small random variation to avoid identical sounds
-            let variation = rng.random_range(-config.speed_variation..=config.speed_variation);
+            let variation = rng.0.random_range(-config.speed_variation..=config.speed_variation);
             let final_pitch = pitch * (1.0 + variation);
 
             // Spawn a new entity with an AudioPlayer that will play once
@@ -106,4 +108,27 @@ fn play_collision_sound(
             active_count.0 += 1;
         }
     }
-}
\ No newline at end of file
+}
+// Pitch of the dull thud played when shooting without ammo
+const REFUSED_SOUND_PITCH: f32 = 0.4;
+
+fn play_refused_sound(
+    mut refused_events: EventReader<ShotRefused>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+    mut active_count: ResMut<ActiveSoundCount>,
+) {
+    // One thud is enough, even if several players tried to shoot
+    if refused_events.read().last().is_none() {
+        return;
+    }
+
+    commands.spawn((
+        AudioPlayer::new(asset_server.load("sounds/ball_hit.flac")),
+        PlaybackSettings {
+            speed: REFUSED_SOUND_PITCH,
+            ..PlaybackSettings::ONCE
+        },
+    ));
+    active_count.0 += 1;
+}
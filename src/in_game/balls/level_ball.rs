@@ -1,46 +1,94 @@
 use crate::in_game::balls::ammo_ball::AmmoBall;
 use avian2d::{math::*, prelude::*};
 use bevy::prelude::*;
-use crate::in_game::balls::initial_velocity::InitialVelocity;
 use crate::in_game::balls::ammo_ball::SplitChain;
+use crate::in_game::balls::split_rule::SplitRule;
+use crate::in_game::levels::properties::PhysicsMaterial;
+use crate::in_game::physics_layers::GameLayer;
+use crate::in_game::simulation::GameplaySystems;
+use crate::in_game::states::{GameState, InLevel};
+use std::collections::HashSet;
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct LevelBall {
     pub static_body: bool,
+    pub radius: f32,
+    pub color: Color,
 }
 
+/// Points awarded for popping the ball
+#[derive(Component, Clone, Copy)]
+pub struct ScoreValue(pub u32);
+
+/// Hits left before the ball splits
+#[derive(Component)]
+pub struct HitPoints(pub u32);
+
+/// Size tier of a level ball. A ball of tier N splits into smaller balls of tier N - 1,
+/// tier 0 balls pop completely.
+#[derive(Component, Clone, Copy)]
+pub struct SizeTier(pub u32);
+
+/// How many splits in a row it took to create this ball
+#[derive(Component, Clone, Copy)]
+pub struct SplitDepth(pub u32);
+
+/// Moving balls stop splitting other balls once their chain reaction reaches this depth
+#[derive(Resource)]
+pub struct MaxChainDepth(pub u32);
+
+impl Default for MaxChainDepth {
+    fn default() -> Self {
+        Self(6)
+    }
+}
+
+/// Sent whenever a level ball is destroyed, whether it split or popped completely
+#[derive(Event, Clone, Debug)]
+pub struct BallPopped {
+    pub position: Vec2,
+    pub tier: u32,
+    pub score: u32,
+    /// The shot that caused this pop, if any
+    pub ammo_id: Option<u32>,
+}
+
+// Each tier is this much smaller than the one above it
+const TIER_RADIUS_SCALE: f32 = 0.7;
+
 #[derive(Component)]
 pub struct PreviousVelocity(pub Vec2);
 
 pub(in crate::in_game) fn level_ball_plugin(app: &mut App) {
-    app.add_observer(observe_level_ball_add)
-        .add_systems(FixedPreUpdate, update_previous_velocity)
-        .add_systems(Update, react_to_ammo_ball_hitting);
+    app.init_resource::<MaxChainDepth>()
+        .add_event::<BallPopped>()
+        .add_observer(observe_level_ball_add)
+        // Collisions from the last physics step are handled with the velocities from before it,
+        // which are only updated afterwards
+        .add_systems(
+            FixedUpdate,
+            (react_to_ball_hitting.run_if(in_state(GameState::Playing)), update_previous_velocity)
+                .chain()
+                .in_set(GameplaySystems::Collide),
+        );
 }
 
-const BALL_RADIUS: f32 = 30.0;
-
 fn observe_level_ball_add(
     trigger: Trigger<OnAdd, LevelBall>,
-    level_ball: Query<&LevelBall>,
+    level_ball: Query<(&LevelBall, Option<&PhysicsMaterial>)>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
 ) {
-    let level_ball = level_ball.get(trigger.target()).unwrap();
-
-    commands.entity(trigger.target()).insert((
-        Sprite {
-            image: asset_server.load("ball.png"),
-            custom_size: Some(Vec2::splat(BALL_RADIUS)),
-            ..Default::default()
-        },
-        Collider::circle(BALL_RADIUS / 2.0 as Scalar),
-        Restitution {
-            coefficient: 1.0,
-            ..Default::default()
-        },
+    let (level_ball, material) = level_ball.get(trigger.target()).unwrap();
+    let material = material.cloned().unwrap_or_default();
+
+    let mut entity_commands = commands.entity(trigger.target());
+    entity_commands.insert((
+        Collider::circle(level_ball.radius as Scalar),
+        material.restitution_or(1.0),
+        material.friction_or_default(),
+        CollisionLayers::new(GameLayer::Ball, LayerMask::ALL),
         CollisionEventsEnabled,
-        Mass(6.0),
+        Mass(material.mass.unwrap_or(6.0)),
         PreviousVelocity(Vec2::ZERO),
         if level_ball.static_body {
             RigidBody::Static
@@ -48,6 +96,7 @@ fn observe_level_ball_add(
             RigidBody::Dynamic
         }
     ));
+    material.insert_sensor(&mut entity_commands);
 }
 
 fn update_previous_velocity(
@@ -58,118 +107,195 @@ fn update_previous_velocity(
     }
 }
 
-fn react_to_ammo_ball_hitting(
+fn react_to_ball_hitting(
     mut event: EventReader<CollisionStarted>,
     transforms: Query<&Transform>,
-    level_ball: Query<&LevelBall>,
+    level_ball: Query<(&LevelBall, Option<&SizeTier>, Option<&SplitDepth>)>,
+    split_rules: Query<&SplitRule>,
+    materials: Query<&PhysicsMaterial>,
+    scores: Query<&ScoreValue>,
+    mut hit_points: Query<&mut HitPoints>,
     split_chains: Query<&SplitChain>,
     ammo_ball: Query<(), With<AmmoBall>>,
     velocities: Query<&PreviousVelocity>,
+    max_chain_depth: Res<MaxChainDepth>,
     mut commands: Commands,
 ) {
-    for CollisionStarted(entity1, entity2) in event.read() {
-        // First, try to find which entity is the static level ball
-        let static_level_ball = if let Ok(ball1) = level_ball.get(*entity1) {
-            if ball1.static_body {
-                Some(*entity1)
-            } else {
-                None
+    // Balls can take part in several collisions in the same frame, but only pop once
+    let mut popped = HashSet::new();
+
+    let popped_ball = |entity: Entity| {
+        let (ball, tier, _) = level_ball.get(entity).unwrap();
+        PoppedBall {
+            entity,
+            ball: ball.clone(),
+            tier: tier.map_or(0, |tier| tier.0),
+            split_rule: split_rules.get(entity).cloned().unwrap_or_default(),
+            material: materials.get(entity).ok().cloned(),
+            score: scores.get(entity).ok().copied(),
+            translation: transforms.get(entity).unwrap().translation,
+        }
+    };
+
+    // The physics engine reports collisions in no particular order, but which ball pops first
+    // decides what happens next, so they're handled sorted by the entities involved
+    let mut collisions: Vec<_> = event
+        .read()
+        .map(|CollisionStarted(entity1, entity2)| (*entity1.min(entity2), *entity1.max(entity2)))
+        .collect();
+    collisions.sort_unstable();
+    collisions.dedup();
+
+    for (entity1, entity2) in collisions {
+        for (target, hitter) in [(entity1, entity2), (entity2, entity1)] {
+            if popped.contains(&target) || popped.contains(&hitter) {
+                continue;
             }
-        } else {
-            None
-        }.or_else(|| {
-            if let Ok(ball2) = level_ball.get(*entity2) {
-                if ball2.static_body {
-                    Some(*entity2)
-                } else {
-                    None
+
+            // Only level balls can be split
+            let Ok((_, _, target_depth)) = level_ball.get(target) else {
+                continue;
+            };
+
+            // Ammo always splits what it hits, moving level balls only until the chain gets too deep
+            let hitter_is_ammo = ammo_ball.contains(hitter);
+            let hitter_depth = match level_ball.get(hitter) {
+                Ok((ball, _, depth)) if !ball.static_body => depth.map_or(0, |depth| depth.0),
+                _ if hitter_is_ammo => 0,
+                _ => continue,
+            };
+            if !hitter_is_ammo && hitter_depth >= max_chain_depth.0 {
+                continue;
+            }
+
+            // Armored balls take several hits before they split
+            if let Ok(mut hit_points) = hit_points.get_mut(target) {
+                if hit_points.0 > 1 {
+                    hit_points.0 -= 1;
+                    continue;
                 }
+            }
+
+            // Propagate the chain of the hitter, or keep the one the target already belongs to
+            let split_chain = split_chains.get(hitter).or_else(|_| split_chains.get(target)).ok().cloned();
+
+            let hitter_position = transforms.get(hitter).unwrap().translation.truncate();
+            let target_position = transforms.get(target).unwrap().translation.truncate();
+
+            // Get the collision direction from the hitter to the target
+            let collision_dir = (target_position - hitter_position).normalize_or(Vec2::X);
+            debug!("collision direction: {:?}", collision_dir);
+
+            let child_depth = hitter_depth.max(target_depth.map_or(0, |depth| depth.0)) + 1;
+
+            // Calculate speed based on the hitter's previous velocity
+            let incoming_velocity = velocities.get(hitter).ok().map(|velocity| velocity.0);
+            pop_ball(
+                &mut commands,
+                popped_ball(target),
+                collision_dir,
+                incoming_velocity,
+                child_depth,
+                split_chain.as_ref(),
+            );
+            popped.insert(target);
+
+            if hitter_is_ammo {
+                commands.entity(hitter).try_despawn();
             } else {
-                None
+                // A moving ball breaks apart too when it hits another ball, carried by its own momentum
+                pop_ball(
+                    &mut commands,
+                    popped_ball(hitter),
+                    collision_dir,
+                    incoming_velocity,
+                    child_depth,
+                    split_chain.as_ref(),
+                );
             }
-        });
+            popped.insert(hitter);
+        }
+    }
+}
 
-        // If we didn't find a static level ball, skip this collision
-        let Some(static_level_ball) = static_level_ball else {
-            continue;
-        };
+struct PoppedBall {
+    entity: Entity,
+    ball: LevelBall,
+    tier: u32,
+    split_rule: SplitRule,
+    material: Option<PhysicsMaterial>,
+    score: Option<ScoreValue>,
+    translation: Vec3,
+}
 
-        // The colliding entity is the other one
-        let colliding_entity = if static_level_ball == *entity1 {
-            *entity2
-        } else {
-            *entity1
-        };
+// Despawns the ball and spawns its split children, unless it's in the smallest tier
+fn pop_ball(
+    commands: &mut Commands,
+    popped: PoppedBall,
+    collision_dir: Vec2,
+    incoming_velocity: Option<Vec2>,
+    child_depth: u32,
+    split_chain: Option<&SplitChain>,
+) {
+    commands.entity(popped.entity).try_despawn();
+    commands.send_event(BallPopped {
+        position: popped.translation.truncate(),
+        tier: popped.tier,
+        score: popped.score.map_or(0, |score| score.0),
+        ammo_id: split_chain.map(|chain| chain.ammo_id),
+    });
 
-        // Get the split chain from the colliding entity if it exists
-        let split_chain = if ammo_ball.contains(colliding_entity) {
-            // If it's an ammo ball, get its ID directly
-            split_chains.get(colliding_entity).ok().map(|chain| chain.clone())
-        } else {
-            // If it's a level ball with a chain, use that
-            split_chains.get(colliding_entity).ok().map(|chain| chain.clone())
-        };
-
-        // Verify the colliding entity is either an ammo ball or a non-static level ball
-        if !ammo_ball.contains(colliding_entity) && 
-           !level_ball.get(colliding_entity).map_or(false, |ball| !ball.static_body) {
-            continue;
+    // Tier 0 balls pop completely
+    if popped.tier == 0 {
+        debug!("ball popped");
+        return;
+    }
+
+    let split_rule = &popped.split_rule;
+    let split_directions = split_rule.directions(collision_dir);
+    debug!("split directions: {:?}", split_directions);
+
+    let child_radius = popped.ball.radius * TIER_RADIUS_SCALE;
+
+    // Create the new split balls, propagating the split chain if it exists
+    for direction in split_directions {
+        let mut entity_commands = commands.spawn((
+            LevelBall {
+                static_body: split_rule.children_static,
+                radius: child_radius,
+                ..popped.ball.clone()
+            },
+            SizeTier(popped.tier - 1),
+            SplitDepth(child_depth),
+            split_rule.clone(),
+            Transform::from_translation(
+                popped.translation + direction.extend(0.0) * (popped.ball.radius + split_rule.gap),
+            ),
+            StateScoped(InLevel),
+        ));
+
+        // Static children just hang in place until something hits them
+        if !split_rule.children_static {
+            let velocity = match incoming_velocity {
+                Some(velocity) => split_rule.child_velocity(direction, velocity),
+                None => direction * split_rule.base_speed,
+            };
+            debug!("split velocity: {:?}", velocity);
+            // Set directly rather than as an impulse, which the mass of the child would scale down
+            entity_commands.insert(LinearVelocity(velocity));
         }
 
-        let position_1 = transforms.get(colliding_entity).unwrap().translation.truncate();
-        let position_2 = transforms.get(static_level_ball).unwrap().translation.truncate();
-        
-        // Get the collision direction from colliding_entity to static_level_ball
-        let collision_dir = (position_2 - position_1).normalize();
-        
-        // Rotate collision direction 90 degrees clockwise and counter-clockwise
-        let angle_away_1 = Vec2::new(-collision_dir.y, collision_dir.x); // 90 degrees clockwise
-        let angle_away_2 = Vec2::new(collision_dir.y, -collision_dir.x); // 90 degrees counter-clockwise
-
-        commands.entity(static_level_ball).try_despawn();
-        commands.entity(colliding_entity).try_despawn();
-
-        println!("collision direction: {:?}", collision_dir);
-        println!("split directions: {:?} and {:?}", angle_away_1, angle_away_2);
-
-        let transform = transforms.get(static_level_ball).unwrap();
-        let translation = transform.translation;
-
-        // Calculate speed based on the colliding entity's previous velocity
-        let base_speed = 500.0;
-        let speed = if let Ok(velocity) = velocities.get(colliding_entity) {
-            let velocity_magnitude = velocity.0.length();
-            println!("previous velocity: {:?}, magnitude: {}", velocity.0, velocity_magnitude);
-            
-            // Scale the velocity magnitude to get a reasonable split speed
-            // We want faster incoming balls to create faster splits
-            let velocity_factor = (velocity_magnitude / 200.0).clamp(0.5, 5.0);
-            base_speed * velocity_factor
-        } else {
-            base_speed
-        };
-        
-        let gap_between_balls = 3.0;
-        
-        println!("final speed: {}", speed);
-
-        // Create the new split balls, propagating the split chain if it exists
-        let mut spawn_ball = |angle: Vec2| {
-            let mut entity_commands = commands.spawn((
-                LevelBall {
-                    static_body: false
-                },
-                Transform::from_translation(translation + angle.extend(0.0) * (BALL_RADIUS / 2.0 + gap_between_balls)),
-                InitialVelocity(angle * speed),
-            ));
-
-            // If this split was caused by a chain reaction, propagate it
-            if let Some(chain) = &split_chain {
-                entity_commands.insert(chain.clone());
-            }
-        };
-        
-        spawn_ball(angle_away_1);
-        spawn_ball(angle_away_2);
+        // Split balls keep the physics material and score of the ball they came from
+        if let Some(material) = &popped.material {
+            entity_commands.insert(material.clone());
+        }
+        if let Some(score) = popped.score {
+            entity_commands.insert(score);
+        }
+
+        // If this split was caused by a chain reaction, propagate it
+        if let Some(chain) = split_chain {
+            entity_commands.insert(chain.clone());
+        }
     }
-}
\ No newline at end of file
+}
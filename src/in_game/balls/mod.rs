@@ -4,14 +4,22 @@ use crate::in_game::balls::ammo_ball::ammo_ball_plugin;
 use crate::in_game::balls::level_ball::level_ball_plugin;
 use crate::in_game::balls::particles::particles_plugin;
 use crate::in_game::balls::audio::audio_plugin;
+use crate::in_game::balls::visuals::visuals_plugin;
 
 pub mod ammo_ball;
 pub mod initial_velocity;
 pub mod level_ball;
 pub mod particles;
 pub mod audio;
+pub mod split_rule;
+pub mod visuals;
 
 pub(super) fn balls_plugin(app: &mut App) {
     app.add_observer(observe_initial_velocity);
-    app.add_plugins((level_ball_plugin, ammo_ball_plugin, particles_plugin, audio_plugin));
+    app.add_plugins((level_ball_plugin, ammo_ball_plugin));
+}
+
+/// Sprites, particles and sounds of the balls
+pub(super) fn balls_presentation_plugin(app: &mut App) {
+    app.add_plugins((visuals_plugin, particles_plugin, audio_plugin));
 }
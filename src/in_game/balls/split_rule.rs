@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use std::f32::consts::PI;
+
+/// How a static level ball splits when something hits it
+#[derive(Component, Clone, Debug)]
+pub struct SplitRule {
+    /// Number of balls spawned by the split, 0 pops the ball completely
+    pub children: u32,
+    /// Angle in radians between the outermost children, centered on the collision direction.
+    /// The default of PI sends two children out perpendicular to the hit.
+    pub spread: f32,
+    /// Speed of the children in world units per second, when the incoming ball moves at `speed_reference`
+    pub base_speed: f32,
+    pub speed_reference: f32,
+    /// The speed factor is `(incoming speed / speed_reference) ^ speed_exponent`, clamped to these
+    pub min_speed_factor: f32,
+    pub max_speed_factor: f32,
+    pub speed_exponent: f32,
+    /// Add the velocity of the incoming ball to the children, so they keep moving along with it
+    pub inherit_momentum: bool,
+    /// Children hang in place like the original ball instead of flying off
+    pub children_static: bool,
+    /// Space between the children and the center of the split ball
+    pub gap: f32,
+}
+
+impl Default for SplitRule {
+    fn default() -> Self {
+        Self {
+            children: 2,
+            spread: PI,
+            base_speed: 500.0,
+            speed_reference: 200.0,
+            min_speed_factor: 0.5,
+            max_speed_factor: 5.0,
+            speed_exponent: 1.0,
+            inherit_momentum: false,
+            children_static: false,
+            gap: 3.0,
+        }
+    }
+}
+
+impl SplitRule {
+    /// Directions of the children, spread evenly around the collision direction
+    pub fn directions(&self, collision_dir: Vec2) -> Vec<Vec2> {
+        (0..self.children)
+            .map(|i| {
+                let t = if self.children == 1 { 0.5 } else { i as f32 / (self.children - 1) as f32 };
+                let angle = (t - 0.5) * self.spread;
+                Vec2::from_angle(angle).rotate(collision_dir)
+            })
+            .collect()
+    }
+
+    /// Split speed for an incoming ball moving at `incoming_speed`
+    pub fn speed(&self, incoming_speed: f32) -> f32 {
+        // We want faster incoming balls to create faster splits
+        let velocity_factor = (incoming_speed / self.speed_reference)
+            .powf(self.speed_exponent)
+            .clamp(self.min_speed_factor, self.max_speed_factor);
+        self.base_speed * velocity_factor
+    }
+
+    /// Velocity a child flying off in `direction` starts with. It's a velocity, not an impulse, so it
+    /// doesn't depend on the mass of the child.
+    pub fn child_velocity(&self, direction: Vec2, incoming_velocity: Vec2) -> Vec2 {
+        let velocity = direction * self.speed(incoming_velocity.length());
+        if self.inherit_momentum {
+            velocity + incoming_velocity
+        } else {
+            velocity
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Vec2, expected: Vec2) {
+        assert!(actual.abs_diff_eq(expected, 1e-5), "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn default_splits_perpendicular_to_the_hit() {
+        let directions = SplitRule::default().directions(Vec2::X);
+        assert_eq!(directions.len(), 2);
+        assert_close(directions[0], Vec2::NEG_Y);
+        assert_close(directions[1], Vec2::Y);
+    }
+
+    #[test]
+    fn single_child_keeps_the_collision_direction() {
+        let rule = SplitRule {
+            children: 1,
+            ..default()
+        };
+        let directions = rule.directions(Vec2::Y);
+        assert_eq!(directions.len(), 1);
+        assert_close(directions[0], Vec2::Y);
+    }
+
+    #[test]
+    fn children_spread_evenly() {
+        let rule = SplitRule {
+            children: 3,
+            spread: PI / 2.0,
+            ..default()
+        };
+        let directions = rule.directions(Vec2::X);
+        assert_close(directions[0], Vec2::from_angle(-PI / 4.0));
+        assert_close(directions[1], Vec2::X);
+        assert_close(directions[2], Vec2::from_angle(PI / 4.0));
+    }
+
+    #[test]
+    fn no_children_pop_the_ball() {
+        let rule = SplitRule {
+            children: 0,
+            ..default()
+        };
+        assert!(rule.directions(Vec2::X).is_empty());
+    }
+
+    #[test]
+    fn speed_scales_with_the_incoming_ball() {
+        let rule = SplitRule::default();
+        assert_eq!(rule.speed(200.0), 500.0);
+        assert_eq!(rule.speed(400.0), 1000.0);
+    }
+
+    #[test]
+    fn speed_factor_is_clamped() {
+        let rule = SplitRule::default();
+        assert_eq!(rule.speed(0.0), 250.0);
+        assert_eq!(rule.speed(100_000.0), 2500.0);
+    }
+
+    #[test]
+    fn children_inherit_momentum() {
+        let rule = SplitRule {
+            inherit_momentum: true,
+            ..default()
+        };
+        let velocity = rule.child_velocity(Vec2::Y, Vec2::new(200.0, 0.0));
+        assert_close(velocity, Vec2::new(200.0, 500.0));
+    }
+}
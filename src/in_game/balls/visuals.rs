@@ -0,0 +1,33 @@
+use crate::in_game::balls::ammo_ball::{AMMO_SIZE, AmmoBall};
+use crate::in_game::balls::level_ball::LevelBall;
+use bevy::color::palettes::basic::GREEN;
+use bevy::prelude::*;
+
+pub(in crate::in_game) fn visuals_plugin(app: &mut App) {
+    app.add_observer(observe_level_ball_add)
+        .add_observer(observe_ammo_ball_add);
+}
+
+fn observe_level_ball_add(
+    trigger: Trigger<OnAdd, LevelBall>,
+    level_balls: Query<&LevelBall>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let level_ball = level_balls.get(trigger.target()).unwrap();
+    commands.entity(trigger.target()).insert(Sprite {
+        image: asset_server.load("ball.png"),
+        custom_size: Some(Vec2::splat(level_ball.radius * 2.0)),
+        color: level_ball.color,
+        ..Default::default()
+    });
+}
+
+fn observe_ammo_ball_add(trigger: Trigger<OnAdd, AmmoBall>, mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.entity(trigger.target()).insert(Sprite {
+        image: asset_server.load("ball.png"),
+        custom_size: Some(Vec2::splat(AMMO_SIZE)),
+        color: GREEN.into(),
+        ..Default::default()
+    });
+}
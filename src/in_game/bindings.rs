@@ -0,0 +1,187 @@
+use bevy::prelude::*;
+use bevy_enhanced_input::prelude::Input;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Player actions whose inputs can be changed in the settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum RebindableAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Shoot,
+    IncreaseForce,
+    DecreaseForce,
+}
+
+impl RebindableAction {
+    pub const ALL: [Self; 7] = [
+        Self::MoveUp,
+        Self::MoveDown,
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::Shoot,
+        Self::IncreaseForce,
+        Self::DecreaseForce,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::MoveUp => "Aim up",
+            Self::MoveDown => "Aim down",
+            Self::MoveLeft => "Move left",
+            Self::MoveRight => "Move right",
+            Self::Shoot => "Shoot",
+            Self::IncreaseForce => "Increase force",
+            Self::DecreaseForce => "Decrease force",
+        }
+    }
+}
+
+/// A key or button an action is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BoundInput {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+    /// Only caught to be refused, the sticks always move and aim
+    Stick(GamepadAxis),
+}
+
+impl BoundInput {
+    /// Each action has at most one input of each kind, a new key replaces the old key
+    fn same_kind(&self, other: &BoundInput) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    pub fn name(&self) -> String {
+        let name = match self {
+            Self::Key(key) => format!("{:?}", key),
+            Self::Mouse(button) => format!("Mouse {:?}", button),
+            Self::Gamepad(button) => format!("Pad {:?}", button),
+            Self::Stick(axis) => format!("Pad {:?}", axis),
+        };
+        // KeyW reads better as W, Digit1 as 1
+        name.strip_prefix("Key")
+            .or_else(|| name.strip_prefix("Digit"))
+            .map(str::to_string)
+            .unwrap_or(name)
+    }
+}
+
+impl From<BoundInput> for Input {
+    fn from(input: BoundInput) -> Self {
+        match input {
+            BoundInput::Key(key) => key.into(),
+            BoundInput::Mouse(button) => button.into(),
+            BoundInput::Gamepad(button) => button.into(),
+            BoundInput::Stick(axis) => axis.into(),
+        }
+    }
+}
+
+/// What an input that can't be rebound is used for: pausing, restarting and the fixed gamepad controls
+pub fn reserved_use(input: BoundInput) -> Option<&'static str> {
+    use BoundInput::*;
+    match input {
+        Key(KeyCode::Escape) | Gamepad(GamepadButton::Start) => Some("pausing"),
+        Key(KeyCode::KeyR) | Gamepad(GamepadButton::Select) => Some("restarting"),
+        Gamepad(GamepadButton::RightTrigger2 | GamepadButton::LeftTrigger2) => Some("setting the force"),
+        Stick(GamepadAxis::LeftStickX | GamepadAxis::LeftStickY) => Some("moving"),
+        Stick(GamepadAxis::RightStickX | GamepadAxis::RightStickY) => Some("aiming"),
+        _ => None,
+    }
+}
+
+/// Why an input can't be bound to an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindingConflict {
+    /// Used for something that can't be rebound, see [`reserved_use`]
+    Reserved(&'static str),
+    Action(RebindableAction),
+}
+
+/// Inputs of the rebindable player actions. The sticks and triggers always move, aim and set the force on top of these.
+#[derive(Resource, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InputBindings {
+    actions: BTreeMap<RebindableAction, Vec<BoundInput>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        use BoundInput::*;
+        Self {
+            actions: BTreeMap::from([
+                (RebindableAction::MoveUp, vec![Key(KeyCode::KeyW)]),
+                (RebindableAction::MoveDown, vec![Key(KeyCode::KeyS)]),
+                (RebindableAction::MoveLeft, vec![Key(KeyCode::KeyA)]),
+                (RebindableAction::MoveRight, vec![Key(KeyCode::KeyD)]),
+                // Not the south button, it also confirms menus and would shoot as soon as the game resumes
+                (
+                    RebindableAction::Shoot,
+                    vec![Key(KeyCode::Space), Mouse(MouseButton::Left), BoundInput::Gamepad(GamepadButton::RightTrigger)],
+                ),
+                (RebindableAction::IncreaseForce, vec![Key(KeyCode::KeyE)]),
+                (RebindableAction::DecreaseForce, vec![Key(KeyCode::KeyQ)]),
+            ]),
+        }
+    }
+}
+
+impl InputBindings {
+    pub fn inputs(&self, action: RebindableAction) -> &[BoundInput] {
+        self.actions.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Gives actions missing from a bindings file, like ones added after it was written, their default inputs
+    pub fn with_missing_defaults(mut self) -> Self {
+        for (action, inputs) in Self::default().actions {
+            self.actions.entry(action).or_insert(inputs);
+        }
+        self
+    }
+
+    /// What already uses this input other than the action, if anything
+    pub fn conflict(&self, action: RebindableAction, input: BoundInput) -> Option<BindingConflict> {
+        if let Some(used_for) = reserved_use(input) {
+            return Some(BindingConflict::Reserved(used_for));
+        }
+        self.actions
+            .iter()
+            .find(|(other, inputs)| **other != action && inputs.contains(&input))
+            .map(|(other, _)| BindingConflict::Action(*other))
+    }
+
+    /// Binds the input to the action, replacing its input of the same kind
+    pub fn rebind(&mut self, action: RebindableAction, input: BoundInput) {
+        let inputs = self.actions.entry(action).or_default();
+        inputs.retain(|bound| !bound.same_kind(&input));
+        inputs.push(input);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_gamepad_inputs_conflict() {
+        let bindings = InputBindings::default();
+        let conflict = |input| bindings.conflict(RebindableAction::Shoot, input);
+
+        assert_eq!(
+            conflict(BoundInput::Gamepad(GamepadButton::LeftTrigger2)),
+            Some(BindingConflict::Reserved("setting the force"))
+        );
+        assert_eq!(
+            conflict(BoundInput::Stick(GamepadAxis::RightStickY)),
+            Some(BindingConflict::Reserved("aiming"))
+        );
+        assert_eq!(
+            conflict(BoundInput::Key(KeyCode::KeyE)),
+            Some(BindingConflict::Action(RebindableAction::IncreaseForce))
+        );
+        assert_eq!(conflict(BoundInput::Gamepad(GamepadButton::South)), None);
+    }
+}
@@ -0,0 +1,392 @@
+use crate::in_game::levels::CurrentLevel;
+use crate::in_game::outcome::LevelCompleted;
+use crate::in_game::replay::ReplayPlayback;
+use crate::in_game::scoring::Score;
+use crate::in_game::states::GameState;
+use bevy::asset::io::Reader;
+use bevy::asset::{ron, AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+pub(super) fn campaign_plugin(app: &mut App) {
+    app.init_asset::<Campaign>()
+        .init_asset_loader::<CampaignLoader>()
+        .init_resource::<CampaignProgress>()
+        .add_event::<StartLevel>()
+        .add_systems(Startup, load_campaign)
+        .add_systems(OnExit(GameState::LevelComplete), cancel_pending_advance)
+        // The score is final by the time this sees the level completed, scoring runs in the fixed steps before
+        .add_systems(
+            Update,
+            (
+                // Watching a replay doesn't count as playing the level
+                complete_level.run_if(not(resource_exists::<ReplayPlayback>)),
+                advance_after_delay,
+                start_level_by_id,
+            )
+                .chain(),
+        );
+}
+
+const CAMPAIGN_PATH: &str = "levels/main.campaign.ron";
+// Time to enjoy the cleared level before the next one starts
+const LEVEL_ADVANCE_DELAY: f32 = 2.0;
+
+/// The ordered list of levels, loaded from a `.campaign.ron` manifest
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct Campaign {
+    pub levels: Vec<CampaignLevel>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CampaignLevel {
+    pub id: String,
+    pub name: String,
+    /// Asset path of the TMX file
+    pub path: String,
+    #[serde(default)]
+    pub unlock: UnlockRequirement,
+    /// Shots a good player needs to clear the level
+    #[serde(default)]
+    pub par_shots: Option<u32>,
+    #[serde(default)]
+    pub par_score: Option<u32>,
+}
+
+impl CampaignLevel {
+    /// One star for completing the level, one for reaching the par score and one for needing no more than par shots
+    pub fn stars(&self, score: u32, shots: u32) -> u32 {
+        1 + self.par_score.is_some_and(|par| score >= par) as u32
+            + self.par_shots.is_some_and(|par| shots <= par) as u32
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub enum UnlockRequirement {
+    /// Unlocked once the level before it in the campaign is completed
+    #[default]
+    Previous,
+    Always,
+    /// Unlocked once all of these level ids are completed
+    Completed(Vec<String>),
+}
+
+impl Campaign {
+    pub fn index_of(&self, id: &str) -> Option<usize> {
+        self.levels.iter().position(|level| level.id == id)
+    }
+
+    pub fn level(&self, id: &str) -> Option<&CampaignLevel> {
+        self.levels.iter().find(|level| level.id == id)
+    }
+
+    pub fn next_level(&self, id: &str) -> Option<&CampaignLevel> {
+        self.levels.get(self.index_of(id)? + 1)
+    }
+
+    /// The level to continue the campaign with: the first unlocked level that isn't completed yet
+    pub fn continue_level(&self, progress: &CampaignProgress) -> Option<&CampaignLevel> {
+        self.levels
+            .iter()
+            .enumerate()
+            .find(|(i, level)| self.is_unlocked(*i, progress) && !progress.completed.contains(&level.id))
+            .map(|(_, level)| level)
+            .or_else(|| self.levels.first())
+    }
+
+    pub fn is_unlocked(&self, index: usize, progress: &CampaignProgress) -> bool {
+        let Some(level) = self.levels.get(index) else {
+            return false;
+        };
+        // Levels stay unlocked once they were, even if the campaign changed since
+        if progress.unlocked.contains(&level.id) {
+            return true;
+        }
+        match &level.unlock {
+            UnlockRequirement::Always => true,
+            UnlockRequirement::Previous => {
+                index == 0 || progress.completed.contains(&self.levels[index - 1].id)
+            }
+            UnlockRequirement::Completed(ids) => ids.iter().all(|id| progress.completed.contains(id)),
+        }
+    }
+}
+
+/// Progress through the campaign, kept between runs by the save file
+#[derive(Resource, Debug, Default)]
+pub struct CampaignProgress {
+    pub completed: HashSet<String>,
+    pub unlocked: HashSet<String>,
+    /// Best results of completed levels, by level id
+    pub records: HashMap<String, LevelRecord>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LevelRecord {
+    pub best_score: u32,
+    pub fewest_shots: u32,
+    pub stars: u32,
+}
+
+impl LevelRecord {
+    /// Keeps the better of the recorded and the new result, separately for score, shots and stars
+    fn update(&mut self, other: LevelRecord) {
+        self.best_score = self.best_score.max(other.best_score);
+        self.fewest_shots = self.fewest_shots.min(other.fewest_shots);
+        self.stars = self.stars.max(other.stars);
+    }
+}
+
+#[derive(Resource)]
+pub struct CampaignHandle(pub Handle<Campaign>);
+
+/// Send to jump to any level of the campaign by its id
+#[derive(Event, Clone, Debug)]
+pub struct StartLevel {
+    pub id: String,
+}
+
+#[derive(Resource)]
+struct PendingAdvance {
+    timer: Timer,
+    next_level: String,
+}
+
+#[derive(Default)]
+pub struct CampaignLoader;
+
+#[derive(Debug)]
+pub enum CampaignLoaderError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+}
+
+impl std::fmt::Display for CampaignLoaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Failed to read campaign file: {}", e),
+            Self::Ron(e) => write!(f, "Failed to parse campaign file: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CampaignLoaderError {}
+
+impl AssetLoader for CampaignLoader {
+    type Asset = Campaign;
+    type Settings = ();
+    type Error = CampaignLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Campaign, CampaignLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await.map_err(CampaignLoaderError::Io)?;
+        ron::de::from_bytes(&bytes).map_err(CampaignLoaderError::Ron)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["campaign.ron"]
+    }
+}
+
+fn load_campaign(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(CampaignHandle(asset_server.load(CAMPAIGN_PATH)));
+}
+
+fn complete_level(
+    mut commands: Commands,
+    mut completed_events: EventReader<LevelCompleted>,
+    mut progress: ResMut<CampaignProgress>,
+    current_level: Option<Res<CurrentLevel>>,
+    score: Res<Score>,
+    campaign_handle: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+) {
+    for completed in completed_events.read() {
+        let Some(current_level) = &current_level else {
+            continue;
+        };
+        let Some(campaign) = campaigns.get(&campaign_handle.0) else {
+            continue;
+        };
+
+        let record = LevelRecord {
+            best_score: score.total,
+            fewest_shots: completed.shots_used,
+            stars: campaign
+                .level(&current_level.id)
+                .map_or(1, |level| level.stars(score.total, completed.shots_used)),
+        };
+        progress.completed.insert(current_level.id.clone());
+        progress
+            .records
+            .entry(current_level.id.clone())
+            .and_modify(|existing| existing.update(record))
+            .or_insert(record);
+
+        // Remember everything this completion unlocked
+        let unlocked: Vec<String> = (0..campaign.levels.len())
+            .filter(|i| campaign.is_unlocked(*i, &progress))
+            .map(|i| campaign.levels[i].id.clone())
+            .collect();
+        progress.unlocked.extend(unlocked);
+        match campaign.next_level(&current_level.id) {
+            Some(next) => commands.insert_resource(PendingAdvance {
+                timer: Timer::from_seconds(LEVEL_ADVANCE_DELAY, TimerMode::Once),
+                next_level: next.id.clone(),
+            }),
+            None => info!("Campaign completed"),
+        }
+    }
+}
+
+// Retrying or leaving for the menu shouldn't start the next level behind the player's back
+fn cancel_pending_advance(mut commands: Commands) {
+    commands.remove_resource::<PendingAdvance>();
+}
+
+fn advance_after_delay(
+    mut commands: Commands,
+    pending: Option<ResMut<PendingAdvance>>,
+    time: Res<Time>,
+    mut start_level: EventWriter<StartLevel>,
+) {
+    let Some(mut pending) = pending else {
+        return;
+    };
+
+    if pending.timer.tick(time.delta()).finished() {
+        start_level.write(StartLevel {
+            id: pending.next_level.clone(),
+        });
+        commands.remove_resource::<PendingAdvance>();
+    }
+}
+
+fn start_level_by_id(
+    mut commands: Commands,
+    mut start_events: EventReader<StartLevel>,
+    campaign_handle: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+) {
+    let Some(campaign) = campaigns.get(&campaign_handle.0) else {
+        start_events.clear();
+        return;
+    };
+
+    for StartLevel { id } in start_events.read() {
+        let Some(level) = campaign.level(id) else {
+            error!("Unknown level id: {}", id);
+            continue;
+        };
+
+        info!("Starting level {}: {}", level.id, level.name);
+        commands.insert_resource(CurrentLevel {
+            id: level.id.clone(),
+            name: level.name.clone(),
+            path: level.path.clone(),
+        });
+        // A level started by hand replaces any pending automatic advance
+        commands.remove_resource::<PendingAdvance>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAMPAIGN: &str = r#"(
+        levels: [
+            (id: "intro", name: "Intro", path: "levels/intro.tmx"),
+            (id: "second", name: "Second", path: "levels/second.tmx"),
+            (id: "bonus", name: "Bonus", path: "levels/bonus.tmx", unlock: Always),
+            (id: "finale", name: "Finale", path: "levels/finale.tmx", unlock: Completed(["intro", "bonus"])),
+        ],
+    )"#;
+
+    fn campaign() -> Campaign {
+        ron::from_str(CAMPAIGN).unwrap()
+    }
+
+    fn progress(completed: &[&str]) -> CampaignProgress {
+        CampaignProgress {
+            completed: completed.iter().map(|id| id.to_string()).collect(),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn main_campaign_parses() {
+        let campaign: Campaign = ron::from_str(include_str!("../../assets/levels/main.campaign.ron")).unwrap();
+        assert!(!campaign.levels.is_empty());
+    }
+
+    #[test]
+    fn fresh_campaign_unlocks_the_first_and_always_levels() {
+        let campaign = campaign();
+        let progress = progress(&[]);
+        let unlocked: Vec<bool> = (0..4).map(|i| campaign.is_unlocked(i, &progress)).collect();
+        assert_eq!(unlocked, [true, false, true, false]);
+    }
+
+    #[test]
+    fn completing_a_level_unlocks_the_next() {
+        let campaign = campaign();
+        let progress = progress(&["intro"]);
+        assert!(campaign.is_unlocked(1, &progress));
+        assert!(!campaign.is_unlocked(3, &progress));
+    }
+
+    #[test]
+    fn completed_requirement_needs_every_level() {
+        let campaign = campaign();
+        assert!(campaign.is_unlocked(3, &progress(&["intro", "bonus"])));
+    }
+
+    #[test]
+    fn next_level_follows_the_manifest() {
+        let campaign = campaign();
+        assert_eq!(campaign.next_level("intro").unwrap().id, "second");
+        assert!(campaign.next_level("finale").is_none());
+        assert!(campaign.next_level("missing").is_none());
+    }
+
+    #[test]
+    fn continue_with_the_first_unfinished_level() {
+        let campaign = campaign();
+        assert_eq!(campaign.continue_level(&progress(&[])).unwrap().id, "intro");
+        assert_eq!(campaign.continue_level(&progress(&["intro"])).unwrap().id, "second");
+    }
+
+    #[test]
+    fn unlocked_levels_stay_unlocked() {
+        let campaign = campaign();
+        let mut progress = progress(&[]);
+        progress.unlocked.insert("finale".to_string());
+        assert!(campaign.is_unlocked(3, &progress));
+        assert!(!campaign.is_unlocked(4, &progress));
+    }
+
+    #[test]
+    fn stars_for_completion_and_par() {
+        let level = CampaignLevel {
+            par_shots: Some(2),
+            par_score: Some(500),
+            ..campaign().levels[0].clone()
+        };
+        assert_eq!(level.stars(100, 5), 1);
+        assert_eq!(level.stars(500, 5), 2);
+        assert_eq!(level.stars(100, 2), 2);
+        assert_eq!(level.stars(800, 1), 3);
+    }
+
+    #[test]
+    fn levels_without_par_get_one_star() {
+        assert_eq!(campaign().levels[0].stars(u32::MAX, 1), 1);
+    }
+}
@@ -0,0 +1,136 @@
+use crate::in_game::levels::CurrentLevel;
+use crate::in_game::player::{Ammo, Player, ShootingForce};
+use crate::in_game::scoring::Score;
+use crate::in_game::states::AppState;
+use bevy::prelude::*;
+
+pub(super) fn hud_plugin(app: &mut App) {
+    app.add_systems(OnEnter(AppState::InGame), spawn_hud).add_systems(
+        Update,
+        (
+            update_force_text,
+            update_ammo_text,
+            update_score_text.run_if(resource_changed::<Score>),
+            update_level_name_text.run_if(resource_exists_and_changed::<CurrentLevel>),
+        )
+            .run_if(in_state(AppState::InGame)),
+    );
+}
+
+#[derive(Component)]
+struct ForceText;
+
+#[derive(Component)]
+struct AmmoText;
+
+#[derive(Component)]
+struct ScoreText;
+
+#[derive(Component)]
+struct ChainText;
+
+#[derive(Component)]
+struct LevelNameText;
+
+const HUD_FONT_SIZE: f32 = 24.0;
+const HUD_MARGIN: f32 = 16.0;
+
+fn hud_text(text: impl Into<String>) -> (Text, TextFont, TextColor) {
+    (
+        Text::new(text),
+        TextFont {
+            font_size: HUD_FONT_SIZE,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+    )
+}
+
+fn spawn_hud(mut commands: Commands, current_level: Option<Res<CurrentLevel>>, score: Res<Score>) {
+    // Texts of things that already exist start out filled in, the rest gets filled in once it changes
+    let level_name = current_level.map(|level| level.name.clone()).unwrap_or_default();
+
+    commands.spawn((
+        Name::new("HUD"),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(HUD_MARGIN),
+            left: Val::Px(HUD_MARGIN),
+            right: Val::Px(HUD_MARGIN),
+            justify_content: JustifyContent::SpaceBetween,
+            ..default()
+        },
+        StateScoped(AppState::InGame),
+        children![
+            (hud_text(level_name), LevelNameText),
+            (
+                Node {
+                    column_gap: Val::Px(HUD_MARGIN * 2.0),
+                    ..default()
+                },
+                children![
+                    (hud_text(score_text(&score)), ScoreText),
+                    (hud_text(chain_text(&score)), ChainText),
+                    (hud_text(""), AmmoText),
+                    (hud_text(""), ForceText),
+                ],
+            ),
+        ],
+    ));
+}
+
+fn score_text(score: &Score) -> String {
+    format!("Score {}", score.total)
+}
+
+fn chain_text(score: &Score) -> String {
+    format!("Chain {}", score.live_chain())
+}
+
+fn update_force_text(
+    forces: Query<&ShootingForce, (With<Player>, Changed<ShootingForce>)>,
+    mut texts: Query<&mut Text, With<ForceText>>,
+) {
+    let Some(force) = forces.iter().next() else {
+        return;
+    };
+    for mut text in texts.iter_mut() {
+        text.0 = format!("Force {:.0}%", force.fraction() * 100.0);
+    }
+}
+
+fn update_ammo_text(
+    // Players are checked when they are added too, players without ammo can shoot forever
+    players: Query<Option<&Ammo>, (With<Player>, Or<(Added<Player>, Changed<Ammo>)>)>,
+    mut texts: Query<&mut Text, With<AmmoText>>,
+) {
+    let Some(ammo) = players.iter().next() else {
+        return;
+    };
+    let ammo_text = match ammo {
+        Some(ammo) => format!("Ammo {}/{}", ammo.remaining, ammo.max),
+        None => "Ammo unlimited".to_string(),
+    };
+    for mut text in texts.iter_mut() {
+        text.0 = ammo_text.clone();
+    }
+}
+
+fn update_score_text(
+    score: Res<Score>,
+    mut score_texts: Query<&mut Text, (With<ScoreText>, Without<ChainText>)>,
+    mut chain_texts: Query<&mut Text, (With<ChainText>, Without<ScoreText>)>,
+) {
+    for mut text in score_texts.iter_mut() {
+        text.0 = score_text(&score);
+    }
+    for mut text in chain_texts.iter_mut() {
+        text.0 = chain_text(&score);
+    }
+}
+
+fn update_level_name_text(current_level: Res<CurrentLevel>, mut texts: Query<&mut Text, With<LevelNameText>>) {
+    for mut text in texts.iter_mut() {
+        text.0 = current_level.name.clone();
+    }
+}
@@ -1,23 +1,43 @@
 use std::f32::consts::PI;
-use crate::in_game::player::Player;
+use crate::in_game::bindings::{InputBindings, RebindableAction};
+use crate::in_game::player::{LevelShootMode, Player};
+use crate::settings::Settings;
 use bevy::prelude::*;
-use bevy::prelude::KeyCode::Space;
 use bevy_enhanced_input::prelude::*;
 
 pub(super) fn input_plugin(app: &mut App) {
+    app.init_resource::<InputBindings>();
     app.add_input_context::<PlayerInputContext>();
+    app.add_input_context::<GameInputContext>();
     app.add_observer(binding);
+    app.add_observer(game_binding);
     app.add_observer(apply_movement);
+    app.add_systems(Startup, spawn_game_input);
 }
 
 #[derive(Debug, InputAction)]
 #[input_action(output = Vec2)]
 struct Move;
 
+/// Where the right stick points the gun
+#[derive(Debug, InputAction)]
+#[input_action(output = Vec2)]
+pub(crate) struct Aim;
+
+/// Changes the force at a rate following how far the triggers are pressed, positive to increase it
+#[derive(Debug, InputAction)]
+#[input_action(output = f32)]
+pub(crate) struct AdjustForce;
+
 #[derive(Debug, InputAction)]
 #[input_action(output = bool)]
 pub(crate) struct Shoot;
 
+/// Shoot in the charging shoot modes, ongoing while held and fired on release
+#[derive(Debug, InputAction)]
+#[input_action(output = bool)]
+pub(crate) struct ChargeShot;
+
 #[derive(Debug, InputAction)]
 #[input_action(output = bool)]
 pub(crate) struct IncreaseForce;
@@ -26,37 +46,109 @@ pub(crate) struct IncreaseForce;
 #[input_action(output = bool)]
 pub(crate) struct DecreaseForce;
 
+#[derive(Debug, InputAction)]
+#[input_action(output = bool)]
+pub(crate) struct Pause;
+
+#[derive(Debug, InputAction)]
+#[input_action(output = bool)]
+pub(crate) struct Restart;
+
 #[derive(InputContext)]
 pub struct PlayerInputContext;
 
-const PLAYER_SPEED: f32 = 5.0;
+/// Actions that work whatever the player is doing, like pausing
+#[derive(InputContext)]
+pub struct GameInputContext;
+
+// Per second, movement is scaled by the frame time so it's the same at any frame rate
+const PLAYER_SPEED: f32 = 300.0;
 const PLAYER_ROTATION_SPEED: f32 = 0.02;
 
+// Players get their actions again whenever the game resumes, so changed bindings apply from then on
 fn binding(
     trigger: Trigger<Binding<PlayerInputContext>>,
-    mut players: Query<&mut Actions<PlayerInputContext>>,
+    mut players: Query<(&mut Actions<PlayerInputContext>, Option<&LevelShootMode>)>,
+    bindings: Res<InputBindings>,
+    settings: Res<Settings>,
 ) {
-    let mut actions = players.get_mut(trigger.target()).unwrap();
-    actions
-        .bind::<Move>()
-        .to((Cardinal::wasd_keys(), Axial::left_stick()))
+    let (mut actions, level_shoot_mode) = players.get_mut(trigger.target()).unwrap();
+    let inputs = |action| bindings.inputs(action).iter().map(|input| Input::from(*input));
+
+    // Each direction is bound on its own, turned into its axis the same way `Cardinal` does it
+    let move_binding = actions.bind::<Move>();
+    for input in inputs(RebindableAction::MoveUp) {
+        move_binding.to(input.with_modifiers(SwizzleAxis::YXZ));
+    }
+    for input in inputs(RebindableAction::MoveDown) {
+        move_binding.to(input.with_modifiers((Negate::all(), SwizzleAxis::YXZ)));
+    }
+    for input in inputs(RebindableAction::MoveLeft) {
+        move_binding.to(input.with_modifiers(Negate::all()));
+    }
+    for input in inputs(RebindableAction::MoveRight) {
+        move_binding.to(input);
+    }
+    // Only sideways on the stick, the right stick does the aiming
+    move_binding
+        .to(GamepadAxis::LeftStickX)
         .with_modifiers((
             DeadZone::default(),
             SmoothNudge::default(),
             Scale::splat(PLAYER_SPEED),
+            DeltaScale,
         ));
     
     actions
-        .bind::<Shoot>()
-        .to(Space).to(MouseButton::Left);
+        .bind::<Aim>()
+        .to(Axial::right_stick())
+        .with_modifiers(DeadZone::default());
+
+    actions
+        .bind::<AdjustForce>()
+        .to((
+            GamepadButton::RightTrigger2,
+            GamepadButton::LeftTrigger2.with_modifiers(Negate::all()),
+        ))
+        .with_modifiers(DeadZone::default());
+
+    // The shoot inputs charge the shot instead of firing it when the shoot mode says so
+    if LevelShootMode::resolve(level_shoot_mode, &settings).charges() {
+        for input in inputs(RebindableAction::Shoot) {
+            actions.bind::<ChargeShot>().to(input);
+        }
+        actions.bind::<ChargeShot>().with_conditions(Release::default());
+    } else {
+        for input in inputs(RebindableAction::Shoot) {
+            actions.bind::<Shoot>().to(input);
+        }
+    }
 
+    for input in inputs(RebindableAction::IncreaseForce) {
+        actions.bind::<IncreaseForce>().to(input);
+    }
+
+    for input in inputs(RebindableAction::DecreaseForce) {
+        actions.bind::<DecreaseForce>().to(input);
+    }
+}
+
+fn spawn_game_input(mut commands: Commands) {
+    commands.spawn((Name::new("Game input"), Actions::<GameInputContext>::default()));
+}
+
+fn game_binding(
+    trigger: Trigger<Binding<GameInputContext>>,
+    mut contexts: Query<&mut Actions<GameInputContext>>,
+) {
+    let mut actions = contexts.get_mut(trigger.target()).unwrap();
     actions
-        .bind::<IncreaseForce>()
-        .to(KeyCode::KeyE);
+        .bind::<Pause>()
+        .to(KeyCode::Escape).to(GamepadButton::Start);
 
     actions
-        .bind::<DecreaseForce>()
-        .to(KeyCode::KeyQ);
+        .bind::<Restart>()
+        .to(KeyCode::KeyR).to(GamepadButton::Select);
 }
 
 fn apply_movement(trigger: Trigger<Fired<Move>>, mut players: Query<&mut Transform, With<Player>>) {
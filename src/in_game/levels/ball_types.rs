@@ -0,0 +1,170 @@
+//! Ball objects in the `balls` layer pick a preset with their Tiled class (`type` in older
+//! Tiled versions), and custom properties override single values of the preset.
+//!
+//! | Property                 | Type  | Meaning                                               |
+//! |--------------------------|-------|-------------------------------------------------------|
+//! | `radius`                 | float | Radius of the ball                                    |
+//! | `static`                 | bool  | Static balls hang in place until hit                  |
+//! | `tier`                   | int   | Size tier, each split goes one tier down, 0 pops      |
+//! | `split_count`            | int   | How many balls it splits into, 0 pops it completely   |
+//! | `split_spread`           | float | Degrees between the outermost split balls             |
+//! | `split_speed`            | float | Speed of the split balls for an average hit, in px/s  |
+//! | `split_speed_reference`  | float | Incoming speed that gives `split_speed`               |
+//! | `split_speed_min`        | float | Minimum factor applied to `split_speed`               |
+//! | `split_speed_max`        | float | Maximum factor applied to `split_speed`               |
+//! | `split_speed_exponent`   | float | Curve of the speed factor, 1.0 is linear              |
+//! | `split_inherit_momentum` | bool  | Split balls also get the velocity of the hitting ball |
+//! | `split_static_children`  | bool  | Split balls hang in place and split again             |
+//! | `color`                  | color | Tint of the ball sprite                               |
+//! | `score`                  | int   | Points for popping the ball                           |
+//! | `hit_points`             | int   | How many hits it takes to split the ball              |
+
+use crate::in_game::balls::split_rule::SplitRule;
+use crate::in_game::levels::properties::TiledProperties;
+use bevy::prelude::*;
+use roxmltree::Node;
+
+#[derive(Debug, Clone)]
+pub struct BallParameters {
+    pub radius: f32,
+    pub static_body: bool,
+    pub tier: u32,
+    pub split_rule: SplitRule,
+    pub color: Color,
+    pub score_value: u32,
+    pub hit_points: u32,
+}
+
+impl Default for BallParameters {
+    fn default() -> Self {
+        Self {
+            radius: 15.0,
+            static_body: true,
+            tier: 1,
+            split_rule: SplitRule::default(),
+            color: Color::WHITE,
+            score_value: 10,
+            hit_points: 1,
+        }
+    }
+}
+
+impl BallParameters {
+    /// Built-in ball types, selected by the class of a Tiled object
+    pub fn preset(kind: &str) -> Option<Self> {
+        let default = Self::default();
+        match kind {
+            "" | "normal" => Some(default),
+            "small" => Some(Self {
+                radius: 10.0,
+                tier: 0,
+                score_value: 5,
+                ..default
+            }),
+            "big" => Some(Self {
+                radius: 25.0,
+                tier: 2,
+                split_rule: SplitRule {
+                    children: 3,
+                    spread: 240f32.to_radians(),
+                    ..default.split_rule
+                },
+                color: Color::srgb(0.6, 0.8, 1.0),
+                score_value: 20,
+                ..default
+            }),
+            "armored" => Some(Self {
+                color: Color::srgb(0.6, 0.6, 0.6),
+                score_value: 30,
+                hit_points: 3,
+                ..default
+            }),
+            "dynamic" => Some(Self {
+                static_body: false,
+                color: Color::srgb(1.0, 0.8, 0.5),
+                ..default
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn from_object(object: Node, properties: &TiledProperties) -> Self {
+        let kind = object.attribute("class").or_else(|| object.attribute("type")).unwrap_or_default();
+        let preset = Self::preset(kind).unwrap_or_else(|| {
+            warn!(
+                "Unknown ball type {} on object {}, using the normal ball",
+                kind,
+                object.attribute("id").unwrap_or("?")
+            );
+            Self::default()
+        });
+
+        Self {
+            radius: properties.get("radius").unwrap_or(preset.radius),
+            static_body: properties.get("static").unwrap_or(preset.static_body),
+            tier: properties.get("tier").unwrap_or(preset.tier),
+            split_rule: split_rule_from_properties(properties, &preset.split_rule),
+            color: properties
+                .get_str("color")
+                .and_then(parse_tiled_color)
+                .unwrap_or(preset.color),
+            score_value: properties.get("score").unwrap_or(preset.score_value),
+            hit_points: properties.get("hit_points").unwrap_or(preset.hit_points),
+        }
+    }
+}
+
+fn split_rule_from_properties(properties: &TiledProperties, preset: &SplitRule) -> SplitRule {
+    SplitRule {
+        children: properties.get("split_count").unwrap_or(preset.children),
+        spread: properties
+            .get::<f32>("split_spread")
+            .map_or(preset.spread, f32::to_radians),
+        base_speed: properties.get("split_speed").unwrap_or(preset.base_speed),
+        speed_reference: properties.get("split_speed_reference").unwrap_or(preset.speed_reference),
+        min_speed_factor: properties.get("split_speed_min").unwrap_or(preset.min_speed_factor),
+        max_speed_factor: properties.get("split_speed_max").unwrap_or(preset.max_speed_factor),
+        speed_exponent: properties.get("split_speed_exponent").unwrap_or(preset.speed_exponent),
+        inherit_momentum: properties.get("split_inherit_momentum").unwrap_or(preset.inherit_momentum),
+        children_static: properties.get("split_static_children").unwrap_or(preset.children_static),
+        gap: preset.gap,
+    }
+}
+
+/// Parses a Tiled color, `#AARRGGBB` or `#RRGGBB`
+pub fn parse_tiled_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        6 => Some(Color::srgb_u8(channel(0)?, channel(2)?, channel(4)?)),
+        8 => Some(Color::srgba_u8(channel(2)?, channel(4)?, channel(6)?, channel(0)?)),
+        _ => {
+            warn!("Invalid color {}", value);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_without_alpha() {
+        assert_eq!(parse_tiled_color("#ff8000"), Some(Color::srgb_u8(255, 128, 0)));
+        assert_eq!(parse_tiled_color("ff8000"), Some(Color::srgb_u8(255, 128, 0)));
+    }
+
+    #[test]
+    fn color_with_alpha_first() {
+        // Tiled writes #AARRGGBB
+        assert_eq!(parse_tiled_color("#80ff0000"), Some(Color::srgba_u8(255, 0, 0, 128)));
+    }
+
+    #[test]
+    fn invalid_colors() {
+        assert_eq!(parse_tiled_color("#fff"), None);
+        assert_eq!(parse_tiled_color("#gg0000"), None);
+        assert_eq!(parse_tiled_color(""), None);
+    }
+}
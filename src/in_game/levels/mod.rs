@@ -1,218 +1,276 @@
 use avian2d::parry::shape::TriMesh;
 use bevy::prelude::*;
 use avian2d::prelude::*;
-use std::path::Path;
 use avian2d::parry::na::Point2;
-use roxmltree::{Document, Node};
-use crate::in_game::balls::level_ball::LevelBall;
-use crate::in_game::player::Player;
+use bevy::asset::LoadState;
+use crate::in_game::aim_preview::AimPreview;
+use crate::in_game::balls::level_ball::{HitPoints, LevelBall, MaxChainDepth, ScoreValue, SizeTier};
+use crate::in_game::outcome::{LevelGoals, LevelProgress};
+use crate::in_game::player::{Ammo, LevelShootMode, Player, PlayerIndex};
+use crate::in_game::states::{AppState, GameState, InLevel};
+use crate::in_game::simulation::LevelSeed;
+use crate::settings::ShootMode;
+
+pub mod ball_types;
+pub mod properties;
+pub mod shapes;
+pub mod tiles;
+pub mod tmx;
+pub mod validate;
+
+use tiles::TmxTileLayer;
+use shapes::StaticShape;
+use tmx::{StaticBody, TmxLevel, TmxLevelLoader};
 
 pub struct LevelLoadingPlugin;
 
 impl Plugin for LevelLoadingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, load_level);
+        app.init_asset::<TmxLevel>()
+            .init_asset_loader::<TmxLevelLoader>()
+            .add_systems(
+                Update,
+                (
+                    // Nothing is loaded until the campaign starts a level
+                    load_level.run_if(resource_exists_and_changed::<CurrentLevel>),
+                    rebuild_on_level_change,
+                    spawn_level.run_if(in_state(GameState::Loading)),
+                )
+                    .chain(),
+            )
+            .add_systems(OnExit(InLevel), clear_level_resources)
+            .add_systems(OnExit(AppState::InGame), unload_level);
     }
 }
 
 #[derive(Component)]
 pub struct LevelCollider;
 
+/// Parent entity of all the tile sprites of one Tiled tile layer
+#[derive(Component)]
+pub struct LevelTileLayer;
+
+// Tile layers are drawn behind everything else, in the order they appear in the map
+const TILE_LAYER_BASE_Z: f32 = -100.0;
+
+/// The level that should be running. `path` is an asset path, relative to the `assets` folder.
 #[derive(Resource, Clone)]
 pub struct CurrentLevel {
+    pub id: String,
+    pub name: String,
     pub path: String,
 }
 
-// Helper struct to track level bounds
-struct LevelBounds {
-    min_x: f32,
-    max_x: f32,
-    min_y: f32,
-    max_y: f32,
-}
-
-impl LevelBounds {
-    fn new() -> Self {
-        Self {
-            min_x: f32::MAX,
-            max_x: f32::MIN,
-            min_y: f32::MAX,
-            max_y: f32::MIN,
-        }
-    }
-
-    fn update(&mut self, x: f32, y: f32) {
-        self.min_x = self.min_x.min(x);
-        self.max_x = self.max_x.max(x);
-        self.min_y = self.min_y.min(y);
-        self.max_y = self.max_y.max(y);
-    }
+#[derive(Resource)]
+struct LevelHandle(Handle<TmxLevel>);
 
-    fn center(&self) -> Vec2 {
-        Vec2::new(
-            (self.min_x + self.max_x) / 2.0,
-            (self.min_y + self.max_y) / 2.0,
-        )
-    }
-}
+/// Why the level file of the current level failed to load, until another level is loaded
+#[derive(Resource, Debug)]
+pub(crate) struct LevelLoadFailed(pub String);
 
 fn load_level(
     mut commands: Commands,
     current_level: Res<CurrentLevel>,
-    level_entities: Query<Entity, Or<(With<LevelCollider>, With<LevelBall>, With<Player>)>>,
+    asset_server: Res<AssetServer>,
+    mut next_state: ResMut<NextState<GameState>>,
 ) {
-    // Only run if CurrentLevel has changed
-    if !current_level.is_changed() {
-        return;
-    }
+    commands.remove_resource::<LevelLoadFailed>();
+    commands.insert_resource(LevelHandle(asset_server.load(current_level.path.clone())));
+    next_state.set(GameState::Loading);
+}
 
-    let path = Path::new(&current_level.path);
-    if !path.exists() {
-        error!("Level file not found: {}", current_level.path);
+fn rebuild_on_level_change(
+    mut events: EventReader<AssetEvent<TmxLevel>>,
+    level_handle: Option<Res<LevelHandle>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(level_handle) = level_handle else {
+        events.clear();
         return;
-    }
+    };
 
-    // Read TMX file content
-    let tmx_content = match std::fs::read_to_string(path) {
-        Ok(content) => content,
-        Err(e) => {
-            error!("Failed to read TMX file: {}", e);
-            return;
+    for event in events.read() {
+        // The file was saved on disk while the level is running, rebuild it
+        if event.is_modified(&level_handle.0) {
+            info!("Level file changed, rebuilding level");
+            next_state.set(GameState::Loading);
         }
+    }
+}
+
+fn spawn_level(
+    mut commands: Commands,
+    level_handle: Option<Res<LevelHandle>>,
+    levels: Res<Assets<TmxLevel>>,
+    asset_server: Res<AssetServer>,
+    current_level: Option<Res<CurrentLevel>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let Some(level_handle) = level_handle else {
+        return;
     };
 
-    // Parse TMX content
-    let doc = match Document::parse(&tmx_content) {
-        Ok(doc) => doc,
-        Err(e) => {
-            error!("Failed to parse TMX file: {}", e);
-            return;
+    let Some(level) = levels.get(&level_handle.0) else {
+        if let Some(LoadState::Failed(e)) = asset_server.get_load_state(&level_handle.0) {
+            error!("Failed to load level: {}", e);
+            commands.remove_resource::<LevelHandle>();
+            commands.insert_resource(LevelLoadFailed(e.to_string()));
         }
+        return;
     };
 
-    // Clean up existing level entities
-    for entity in level_entities.iter() {
-        commands.entity(entity).despawn();
-    }
+    // The entities of the previous level were already despawned when leaving it,
+    // everything spawned here is scoped to the level in turn
+    next_state.set(GameState::Playing);
 
-    // Calculate level bounds first
-    let mut bounds = LevelBounds::new();
-    for object_group in doc.descendants().filter(|n| n.has_tag_name("objectgroup")) {
-        calculate_layer_bounds(&mut bounds, object_group);
-    }
-
-    // Calculate the offset to center the level
-    let center_offset = bounds.center();
+    commands.insert_resource(
+        level
+            .properties
+            .get("max_chain_depth")
+            .map_or_else(MaxChainDepth::default, MaxChainDepth),
+    );
+    commands.insert_resource(LevelGoals::from_properties(&level.properties));
+    commands.insert_resource(LevelProgress::default());
+    commands.insert_resource(AimPreview::from_properties(&level.properties));
+    commands.insert_resource(level.properties.get("seed").map_or_else(
+        || LevelSeed::from_path(current_level.as_ref().map_or("", |level| level.path.as_str())),
+        LevelSeed,
+    ));
 
-    // Process object layers with centering offset
-    for object_group in doc.descendants().filter(|n| n.has_tag_name("objectgroup")) {
-        match object_group.attribute("name") {
-            Some("static") => process_static_geometry(&mut commands, object_group, center_offset),
-            Some("balls") => process_balls(&mut commands, object_group, center_offset),
-            Some("player") => process_players(&mut commands, object_group, center_offset),
-            _ => continue,
-        }
+    for (i, layer) in level.tile_layers.iter().enumerate() {
+        spawn_tile_layer(&mut commands, level, layer, TILE_LAYER_BASE_Z + i as f32);
     }
-}
 
-fn calculate_layer_bounds(bounds: &mut LevelBounds, object_group: Node) {
-    for object in object_group.children().filter(|n| n.has_tag_name("object")) {
-        let x = object.attribute("x").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
-        let y = object.attribute("y").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
-        let y = -y; // Invert Y coordinate
-
-        bounds.update(x, y);
-
-        // For polygon objects, also check their points
-        if let Some(polygon) = object.children().find(|n| n.has_tag_name("polygon")) {
-            if let Some(points_str) = polygon.attribute("points") {
-                for point_str in points_str.split_whitespace() {
-                    let mut coords = point_str.split(',');
-                    if let (Some(dx), Some(dy)) = (
-                        coords.next().and_then(|s| s.parse::<f32>().ok()),
-                        coords.next().and_then(|s| s.parse::<f32>().ok()),
-                    ) {
-                        bounds.update(x + dx, y - dy);
-                    }
-                }
-            }
-        }
+    for body in &level.static_bodies {
+        spawn_collision_body(&mut commands, body);
     }
-}
 
-fn process_static_geometry(commands: &mut Commands, object_group: Node, center_offset: Vec2) {
-    for object in object_group.children().filter(|n| n.has_tag_name("object")) {
-        // Find polygon child element
-        if let Some(polygon) = object.children().find(|n| n.has_tag_name("polygon")) {
-            // Get object position
-            let x = object.attribute("x").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
-            let y = object.attribute("y").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
-            // Invert Y coordinate
-            let y = -y;
-            
-            // Parse polygon points
-            if let Some(points_str) = polygon.attribute("points") {
-                let points = parse_polygon_points(points_str, x - center_offset.x, y - center_offset.y);
-                spawn_collision_body(commands, points);
-            }
-        }
-    }
-}
-
-fn process_balls(commands: &mut Commands, object_group: Node, center_offset: Vec2) {
-    for object in object_group.children().filter(|n| n.has_tag_name("object")) {
-        // Get ball position
-        let x = object.attribute("x").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
-        let y = object.attribute("y").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
-        // Invert Y coordinate and apply centering offset
-        let y = -y;
-        
+    for ball in &level.ball_spawns {
         // Spawn a level ball at this position
+        let parameters = &ball.parameters;
         commands.spawn((
             LevelBall {
-                static_body: true
+                static_body: parameters.static_body,
+                radius: parameters.radius,
+                color: parameters.color,
             },
-            Transform::from_xyz(x - center_offset.x, y - center_offset.y, 0.0),
+            SizeTier(parameters.tier),
+            parameters.split_rule.clone(),
+            ScoreValue(parameters.score_value),
+            HitPoints(parameters.hit_points),
+            ball.material.clone(),
+            Transform::from_translation(ball.position.extend(0.0)),
+            StateScoped(InLevel),
         ));
     }
-}
 
-fn process_players(commands: &mut Commands, object_group: Node, center_offset: Vec2) {
-    for object in object_group.children().filter(|n| n.has_tag_name("object")) {
-        // Get player position
-        let x = object.attribute("x").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
-        let y = object.attribute("y").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
-        // Invert Y coordinate and apply centering offset
-        let y = -y;
-        
+    let ammo = level.properties.get::<u32>("ammo");
+    let shoot_mode = level.properties.get_str("shoot_mode").and_then(|value| {
+        let mode = ShootMode::from_property(value);
+        if mode.is_none() {
+            warn!("Unknown shoot mode {}, using the setting", value);
+        }
+        mode
+    });
+    for (i, position) in level.player_spawns.iter().enumerate() {
         // Spawn a player at this position
-        commands.spawn((
+        let mut entity_commands = commands.spawn((
             Player,
-            Transform::from_xyz(x - center_offset.x, y - center_offset.y, 0.0),
+            PlayerIndex(i as u32),
+            Transform::from_translation(position.extend(0.0)),
+            StateScoped(InLevel),
         ));
+        if let Some(ammo) = ammo {
+            entity_commands.insert(Ammo::new(ammo));
+        }
+        if let Some(shoot_mode) = shoot_mode {
+            entity_commands.insert(LevelShootMode(shoot_mode));
+        }
     }
 }
 
-fn parse_polygon_points(points_str: &str, base_x: f32, base_y: f32) -> Vec<Vec2> {
-    let mut points: Vec<Vec2> = points_str
-        .split_whitespace()
-        .filter_map(|point_str| {
-            let mut coords = point_str.split(',');
-            let x = coords.next()?.parse::<f32>().ok()?;
-            let y = coords.next()?.parse::<f32>().ok()?;
-            // Invert Y coordinate for relative points
-            Some(Vec2::new(base_x + x, base_y - y))
-        })
-        .collect();
-    
-    // Reverse the winding order to maintain correct orientation after Y-flip
-    points.reverse();
-    points
+fn clear_level_resources(mut commands: Commands) {
+    commands.remove_resource::<LevelGoals>();
+    commands.remove_resource::<LevelProgress>();
+}
+
+// The next level started from the menu gets loaded fresh, instead of spawning the last one for a frame
+fn unload_level(mut commands: Commands) {
+    commands.remove_resource::<LevelHandle>();
+}
+
+fn spawn_tile_layer(commands: &mut Commands, level: &TmxLevel, layer: &TmxTileLayer, z: f32) {
+    commands
+        .spawn((
+            LevelTileLayer,
+            Name::new(layer.name.clone()),
+            Transform::from_xyz(0.0, 0.0, z),
+            Visibility::default(),
+            StateScoped(InLevel),
+        ))
+        .with_children(|parent| {
+            for tile in &layer.tiles {
+                let tileset = &level.tilesets[tile.tileset];
+                let mut sprite = Sprite::from_atlas_image(
+                    tileset.image.clone(),
+                    TextureAtlas {
+                        layout: tileset.layout.clone(),
+                        index: tile.index as usize,
+                    },
+                );
+                sprite.flip_x = tile.flip_x;
+                sprite.color = Color::WHITE.with_alpha(layer.opacity);
+
+                parent.spawn((
+                    sprite,
+                    Transform::from_translation(tile.position.extend(0.0))
+                        .with_rotation(Quat::from_rotation_z(tile.rotation)),
+                ));
+            }
+        });
+}
+
+fn spawn_collision_body(commands: &mut Commands, body: &StaticBody) {
+    let Some((collider, transform)) = static_collider(&body.shape) else {
+        return;
+    };
+
+    let mut entity_commands = commands.spawn((
+        RigidBody::Static,
+        collider,
+        transform,
+        body.material.restitution_or(0.5),
+        body.material.friction_or_default(),
+        body.material.static_collision_layers(),
+        LevelCollider,
+        StateScoped(InLevel),
+    ));
+    body.material.insert_sensor(&mut entity_commands);
 }
 
-fn spawn_collision_body(commands: &mut Commands, points: Vec<Vec2>) {
+// Collider of a static shape and where it goes, `None` for polygons that can't be triangulated
+fn static_collider(shape: &StaticShape) -> Option<(Collider, Transform)> {
+    Some(match shape {
+        StaticShape::Polygon(points) => (polygon_collider(points)?, Transform::default()),
+        StaticShape::Polyline(points) => (Collider::polyline(points.clone(), None), Transform::default()),
+        StaticShape::Rectangle { center, size, rotation } => (
+            Collider::rectangle(size.x, size.y),
+            Transform::from_translation(center.extend(0.0)).with_rotation(Quat::from_rotation_z(*rotation)),
+        ),
+        StaticShape::Ellipse { center, half_size, rotation } => (
+            if half_size.x == half_size.y {
+                Collider::circle(half_size.x)
+            } else {
+                Collider::ellipse(half_size.x, half_size.y)
+            },
+            Transform::from_translation(center.extend(0.0)).with_rotation(Quat::from_rotation_z(*rotation)),
+        ),
+    })
+}
+
+fn polygon_collider(points: &[Vec2]) -> Option<Collider> {
     if points.len() < 3 {
-        return; // Need at least 3 points for a polygon
+        return None; // Need at least 3 points for a polygon
     }
 
     // Convert Vec2 points to Point2<f32> format for TriMesh::from_polygon
@@ -221,26 +279,19 @@ fn spawn_collision_body(commands: &mut Commands, points: Vec<Vec2>) {
         .collect();
 
     // Create the trimesh from the polygon points
-    if let Some(trimesh) = TriMesh::from_polygon(points_array) {
-        let vertices: Vec<Vec2> = trimesh.vertices()
-            .iter()
-            .map(|p| Vec2::new(p.x, p.y))
-            .collect();
-        
-        let indices: Vec<[u32; 3]> = trimesh.indices().to_vec();
-
-        commands.spawn((
-            RigidBody::Static,
-            Collider::trimesh(vertices, indices),
-            Restitution {
-                coefficient: 0.5,
-                ..Default::default()
-            },
-            LevelCollider,
-        ));
-    } else {
+    let Some(trimesh) = TriMesh::from_polygon(points_array) else {
         warn!("Failed to create trimesh from polygon points");
-    }
+        return None;
+    };
+
+    let vertices: Vec<Vec2> = trimesh.vertices()
+        .iter()
+        .map(|p| Vec2::new(p.x, p.y))
+        .collect();
+
+    let indices: Vec<[u32; 3]> = trimesh.indices().to_vec();
+
+    Some(Collider::trimesh(vertices, indices))
 }
 
 fn triangulate_polygon(points: &[Vec2]) -> Vec<[u32; 3]> {
@@ -0,0 +1,153 @@
+//! Tiled custom properties.
+//!
+//! Physics properties can be set on an object or on its whole layer, object values win.
+//!
+//! | Property          | Type   | Applies to           | Meaning                                         |
+//! |-------------------|--------|----------------------|-------------------------------------------------|
+//! | `restitution`     | float  | static bodies, balls | Bounciness, 0.0 absorbs all energy, 1.0 keeps it |
+//! | `friction`        | float  | static bodies, balls | Surface friction, 0.0 is ice                    |
+//! | `mass`            | float  | balls                | Mass of the ball                                |
+//! | `sensor`          | bool   | static bodies, balls | Detects collisions without a physical response  |
+//! | `collision_layer` | string | static bodies        | `all`, `balls` or `ammo`: what the body blocks  |
+//!
+//! Properties of the map itself configure the whole level.
+//!
+//! | Property          | Type   | Meaning                                                          |
+//! |-------------------|--------|------------------------------------------------------------------|
+//! | `max_chain_depth` | int    | How many splits in a row moving balls can cause, default 6       |
+//! | `goal`            | string | `pop_all` (default), `pop_count` or `one_shot`                   |
+//! | `goal_count`      | int    | Balls to pop for `pop_count`, or with a single shot for `one_shot` |
+//! | `ammo`            | int    | Shots the player gets, the level fails when the goal isn't reached with them. Unlimited when not set |
+//! | `aim_preview`     | float  | Length of the predicted shot path, 0 turns it off, default 1500  |
+//! | `aim_preview_bounces` | int | Bounces the predicted shot path follows, default 2              |
+//! | `seed`            | int    | Seed of everything random in the level, taken from the level path when not set |
+//! | `shoot_mode`      | string | `stepped`, `charge` or `swing`: how shots get their force. The player's setting when not set |
+
+use crate::in_game::physics_layers::GameLayer;
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use roxmltree::Node;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// The `<properties>` of a Tiled map, layer or object, by name
+#[derive(Debug, Clone, Default)]
+pub struct TiledProperties(HashMap<String, String>);
+
+impl TiledProperties {
+    pub fn parse(node: Node) -> Self {
+        let Some(properties) = node.children().find(|n| n.has_tag_name("properties")) else {
+            return Self::default();
+        };
+
+        Self(
+            properties
+                .children()
+                .filter(|n| n.has_tag_name("property"))
+                .filter_map(|property| {
+                    let name = property.attribute("name")?;
+                    // Multi-line string properties store their value as text instead of an attribute
+                    let value = property.attribute("value").or_else(|| property.text())?;
+                    Some((name.to_string(), value.to_string()))
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns these properties with the ones in `overrides` replacing them
+    pub fn merged(&self, overrides: &TiledProperties) -> Self {
+        let mut merged = self.0.clone();
+        merged.extend(overrides.0.iter().map(|(k, v)| (k.clone(), v.clone())));
+        Self(merged)
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Parses a property, warning and returning `None` if it has an invalid value
+    pub fn get<T: FromStr>(&self, name: &str) -> Option<T> {
+        let value = self.0.get(name)?;
+        match value.parse::<T>() {
+            Ok(value) => Some(value),
+            Err(_) => {
+                warn!("Invalid value {} for property {}", value, name);
+                None
+            }
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+}
+
+/// What a static body collides with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionLayerProperty {
+    All,
+    Balls,
+    Ammo,
+}
+
+impl FromStr for CollisionLayerProperty {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(Self::All),
+            "balls" => Ok(Self::Balls),
+            "ammo" => Ok(Self::Ammo),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Physics overrides read from Tiled custom properties, `None` keeps the spawner's default
+#[derive(Component, Debug, Clone, Default)]
+pub struct PhysicsMaterial {
+    pub restitution: Option<f32>,
+    pub friction: Option<f32>,
+    pub mass: Option<f32>,
+    pub sensor: bool,
+    pub collision_layer: Option<CollisionLayerProperty>,
+}
+
+impl PhysicsMaterial {
+    pub fn from_properties(properties: &TiledProperties) -> Self {
+        Self {
+            restitution: properties.get("restitution"),
+            friction: properties.get("friction"),
+            mass: properties.get("mass"),
+            sensor: properties.get("sensor").unwrap_or(false),
+            collision_layer: properties.get("collision_layer"),
+        }
+    }
+
+    pub fn restitution_or(&self, default: f32) -> Restitution {
+        Restitution {
+            coefficient: self.restitution.unwrap_or(default),
+            ..Default::default()
+        }
+    }
+
+    pub fn friction_or_default(&self) -> Friction {
+        self.friction.map(Friction::new).unwrap_or_default()
+    }
+
+    /// Collision layers for a static body with this material
+    pub fn static_collision_layers(&self) -> CollisionLayers {
+        match self.collision_layer.unwrap_or(CollisionLayerProperty::All) {
+            CollisionLayerProperty::All => CollisionLayers::new(GameLayer::Default, LayerMask::ALL),
+            CollisionLayerProperty::Balls => CollisionLayers::new(GameLayer::Default, GameLayer::Ball),
+            CollisionLayerProperty::Ammo => CollisionLayers::new(GameLayer::Default, GameLayer::Ammo),
+        }
+    }
+
+    /// Inserts the sensor marker if this material asks for one
+    pub fn insert_sensor(&self, entity_commands: &mut EntityCommands) {
+        if self.sensor {
+            entity_commands.insert(Sensor);
+        }
+    }
+}
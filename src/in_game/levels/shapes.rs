@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+use roxmltree::Node;
+
+/// Static level geometry in world space
+#[derive(Debug, Clone)]
+pub enum StaticShape {
+    Polygon(Vec<Vec2>),
+    Polyline(Vec<Vec2>),
+    Rectangle { center: Vec2, size: Vec2, rotation: f32 },
+    Ellipse { center: Vec2, half_size: Vec2, rotation: f32 },
+}
+
+impl StaticShape {
+    /// Points that enclose the shape, used for the level bounds
+    pub fn outline(&self) -> Vec<Vec2> {
+        match self {
+            StaticShape::Polygon(points) | StaticShape::Polyline(points) => points.clone(),
+            StaticShape::Rectangle { center, size, rotation } => box_corners(*center, *size / 2.0, *rotation),
+            StaticShape::Ellipse { center, half_size, rotation } => box_corners(*center, *half_size, *rotation),
+        }
+    }
+}
+
+fn box_corners(center: Vec2, half_size: Vec2, rotation: f32) -> Vec<Vec2> {
+    let rotation = Vec2::from_angle(rotation);
+    [
+        Vec2::new(-half_size.x, -half_size.y),
+        Vec2::new(half_size.x, -half_size.y),
+        Vec2::new(half_size.x, half_size.y),
+        Vec2::new(-half_size.x, half_size.y),
+    ]
+    .into_iter()
+    .map(|corner| center + rotation.rotate(corner))
+    .collect()
+}
+
+/// Parses a Tiled `<object>` into a world space shape, or an error message if the object type
+/// isn't supported as static geometry.
+pub(super) fn parse_object_shape(object: Node, center_offset: Vec2) -> Result<StaticShape, String> {
+    let x = object.attribute("x").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+    let y = object.attribute("y").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+    let width = object.attribute("width").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+    let height = object.attribute("height").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+    // Tiled rotates clockwise in degrees around the object origin, which is counter-clockwise
+    // negative once the Y axis points up
+    let rotation = -object
+        .attribute("rotation")
+        .and_then(|s| s.parse::<f32>().ok())
+        .unwrap_or(0.0)
+        .to_radians();
+
+    // Invert Y coordinate and apply centering offset
+    let origin = Vec2::new(x, -y) - center_offset;
+    let rotate = |local: Vec2| origin + Vec2::from_angle(rotation).rotate(local);
+
+    if object.has_attribute("gid") {
+        return Err("tile objects are not supported as static geometry".to_string());
+    }
+
+    // Tiled writes the custom properties of an object before its shape
+    let shape_node = object
+        .children()
+        .find(|n| n.is_element() && !n.has_tag_name("properties"));
+    match shape_node.map(|n| n.tag_name().name()) {
+        Some("polygon") => {
+            let mut points: Vec<Vec2> = parse_points(shape_node.unwrap())?.into_iter().map(rotate).collect();
+            // Reverse the winding order to maintain correct orientation after Y-flip
+            points.reverse();
+            Ok(StaticShape::Polygon(points))
+        }
+        Some("polyline") => {
+            let points: Vec<Vec2> = parse_points(shape_node.unwrap())?.into_iter().map(rotate).collect();
+            if points.len() < 2 {
+                return Err("polyline needs at least 2 points".to_string());
+            }
+            Ok(StaticShape::Polyline(points))
+        }
+        Some("ellipse") => {
+            if width <= 0.0 || height <= 0.0 {
+                return Err("ellipse has no size".to_string());
+            }
+            Ok(StaticShape::Ellipse {
+                center: rotate(Vec2::new(width, -height) / 2.0),
+                half_size: Vec2::new(width, height) / 2.0,
+                rotation,
+            })
+        }
+        // A plain object with a size is a rectangle
+        None if width > 0.0 && height > 0.0 => Ok(StaticShape::Rectangle {
+            center: rotate(Vec2::new(width, -height) / 2.0),
+            size: Vec2::new(width, height),
+            rotation,
+        }),
+        None => Err("object has no shape or size".to_string()),
+        Some(other) => Err(format!("{} objects are not supported as static geometry", other)),
+    }
+}
+
+// Parses a `points` attribute into points relative to the object origin, Y-up
+fn parse_points(node: Node) -> Result<Vec<Vec2>, String> {
+    let Some(points_str) = node.attribute("points") else {
+        return Err(format!("{} has no points", node.tag_name().name()));
+    };
+
+    points_str
+        .split_whitespace()
+        .map(|point_str| {
+            let mut coords = point_str.split(',');
+            let x = coords.next().and_then(|s| s.parse::<f32>().ok());
+            let y = coords.next().and_then(|s| s.parse::<f32>().ok());
+            match (x, y) {
+                // Invert Y coordinate for relative points
+                (Some(x), Some(y)) => Ok(Vec2::new(x, -y)),
+                _ => Err(format!("invalid point {}", point_str)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roxmltree::Document;
+
+    fn parse(xml: &str) -> Result<StaticShape, String> {
+        let doc = Document::parse(xml).unwrap();
+        parse_object_shape(doc.root_element(), Vec2::ZERO)
+    }
+
+    #[test]
+    fn polygon_with_properties() {
+        let shape = parse(
+            r#"<object id="1" x="10" y="20">
+                <properties><property name="restitution" type="float" value="0.9"/></properties>
+                <polygon points="0,0 10,0 10,10"/>
+            </object>"#,
+        );
+        let Ok(StaticShape::Polygon(points)) = shape else {
+            panic!("expected a polygon, got {:?}", shape);
+        };
+        // Y-flipped and with the winding reversed
+        assert_eq!(points, vec![Vec2::new(20.0, -30.0), Vec2::new(20.0, -20.0), Vec2::new(10.0, -20.0)]);
+    }
+
+    #[test]
+    fn rectangle_with_properties() {
+        let shape = parse(
+            r#"<object id="2" x="0" y="0" width="40" height="20">
+                <properties><property name="friction" type="float" value="0.1"/></properties>
+            </object>"#,
+        );
+        let Ok(StaticShape::Rectangle { center, size, rotation }) = shape else {
+            panic!("expected a rectangle, got {:?}", shape);
+        };
+        assert_eq!(center, Vec2::new(20.0, -10.0));
+        assert_eq!(size, Vec2::new(40.0, 20.0));
+        assert_eq!(rotation, 0.0);
+    }
+
+    #[test]
+    fn polyline_needs_two_points() {
+        let shape = parse(r#"<object id="3" x="0" y="0"><polyline points="5,5"/></object>"#);
+        assert!(shape.is_err());
+    }
+
+    #[test]
+    fn tile_objects_are_rejected() {
+        let shape = parse(r#"<object id="4" gid="3" x="0" y="0" width="32" height="32"/>"#);
+        assert!(shape.is_err());
+    }
+}
@@ -0,0 +1,668 @@
+use crate::in_game::levels::shapes::StaticShape;
+use crate::in_game::levels::tmx::TmxLevelLoaderError;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use bevy::asset::AssetPath;
+use bevy::prelude::*;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use roxmltree::Node;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+// Tiled stores tile flips in the highest bits of each gid
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const ROTATED_HEXAGONAL_120: u32 = 0x1000_0000;
+const GID_MASK: u32 = !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL_120);
+
+/// A tileset with a single atlas image, either inline in the map or from an external `.tsx` file.
+#[derive(Debug, Clone)]
+pub struct TmxTileset {
+    pub first_gid: u32,
+    pub name: String,
+    pub tile_size: UVec2,
+    pub columns: u32,
+    pub tile_count: u32,
+    pub spacing: u32,
+    pub margin: u32,
+    /// Asset path of the atlas image
+    pub image_path: String,
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    /// Collision shapes drawn in Tiled's tile collision editor, by local tile id
+    pub collision_shapes: HashMap<u32, Vec<TileShape>>,
+}
+
+/// A collision shape in tile-local pixel coordinates, Y-down with the origin at the top-left of the tile
+#[derive(Debug, Clone)]
+pub enum TileShape {
+    Rectangle(Rect),
+    Polygon(Vec<Vec2>),
+}
+
+impl TmxTileset {
+    pub fn rows(&self) -> u32 {
+        self.tile_count.div_ceil(self.columns.max(1))
+    }
+
+    // A tile whose only shape covers the whole grid cell can be merged with its solid neighbours
+    fn is_solid(&self, index: u32, grid_size: Vec2) -> bool {
+        let tile_size = self.tile_size.as_vec2();
+        match self.collision_shapes.get(&index).map(Vec::as_slice) {
+            Some([TileShape::Rectangle(rect)]) => {
+                tile_size == grid_size
+                    && rect.min.abs_diff_eq(Vec2::ZERO, 0.5)
+                    && rect.max.abs_diff_eq(tile_size, 0.5)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TmxTileLayer {
+    pub name: String,
+    pub opacity: f32,
+    pub tiles: Vec<TmxTile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TmxTile {
+    /// World space center of the tile image
+    pub position: Vec2,
+    pub tileset: usize,
+    /// Tile index inside the tileset atlas
+    pub index: u32,
+    pub flip_x: bool,
+    pub rotation: f32,
+}
+
+/// Returns the resolved asset paths of all external tilesets referenced by a map.
+pub(super) fn external_tileset_paths(
+    map_path: &AssetPath,
+    map: Node,
+) -> Result<Vec<AssetPath<'static>>, TmxLevelLoaderError> {
+    map.children()
+        .filter(|n| n.has_tag_name("tileset"))
+        .filter_map(|tileset| tileset.attribute("source"))
+        .map(|source| resolve_path(map_path, source))
+        .collect()
+}
+
+pub(super) fn resolve_path(
+    base: &AssetPath,
+    relative: &str,
+) -> Result<AssetPath<'static>, TmxLevelLoaderError> {
+    base.resolve_embed(relative)
+        .map_err(|e| TmxLevelLoaderError::InvalidData(format!("Invalid path {}: {}", relative, e)))
+}
+
+/// Parses a `<tileset>` element. `tileset_path` is the file the element lives in, which is the
+/// map itself for inline tilesets and the `.tsx` file for external ones.
+pub(super) fn parse_tileset(
+    first_gid: u32,
+    tileset: Node,
+    tileset_path: &AssetPath,
+) -> Result<TmxTileset, TmxLevelLoaderError> {
+    let name = tileset.attribute("name").unwrap_or_default().to_string();
+    let tile_width = parse_attribute(tileset, "tilewidth")?;
+    let tile_height = parse_attribute(tileset, "tileheight")?;
+    let spacing = parse_attribute(tileset, "spacing").unwrap_or(0);
+    let margin = parse_attribute(tileset, "margin").unwrap_or(0);
+
+    let Some(image) = tileset.children().find(|n| n.has_tag_name("image")) else {
+        return Err(TmxLevelLoaderError::InvalidData(format!(
+            "Tileset {} has no atlas image, image collection tilesets are not supported",
+            name
+        )));
+    };
+    let Some(source) = image.attribute("source") else {
+        return Err(TmxLevelLoaderError::InvalidData(format!("Tileset {} image has no source", name)));
+    };
+
+    // Older Tiled versions don't write columns and tilecount, derive them from the image size
+    let invalid_size = || {
+        TmxLevelLoaderError::InvalidData(format!(
+            "Tileset {} image is smaller than its margin or has tiles without a size",
+            name
+        ))
+    };
+    let columns = match parse_attribute(tileset, "columns") {
+        Ok(columns) => columns,
+        Err(_) => atlas_cells(parse_attribute(image, "width")?, tile_width, margin, spacing).ok_or_else(invalid_size)?,
+    };
+    let tile_count = match parse_attribute(tileset, "tilecount") {
+        Ok(tile_count) => tile_count,
+        Err(_) => {
+            let rows =
+                atlas_cells(parse_attribute(image, "height")?, tile_height, margin, spacing).ok_or_else(invalid_size)?;
+            columns.checked_mul(rows).ok_or_else(invalid_size)?
+        }
+    };
+
+    let mut collision_shapes = HashMap::new();
+    for tile in tileset.children().filter(|n| n.has_tag_name("tile")) {
+        let Some(object_group) = tile.children().find(|n| n.has_tag_name("objectgroup")) else {
+            continue;
+        };
+        let id: u32 = parse_attribute(tile, "id")?;
+        let shapes: Vec<TileShape> = object_group
+            .children()
+            .filter(|n| n.has_tag_name("object"))
+            .filter_map(|object| parse_tile_shape(&name, id, object))
+            .collect();
+        if !shapes.is_empty() {
+            collision_shapes.insert(id, shapes);
+        }
+    }
+
+    Ok(TmxTileset {
+        first_gid,
+        name,
+        tile_size: UVec2::new(tile_width, tile_height),
+        columns,
+        tile_count,
+        spacing,
+        margin,
+        image_path: resolve_path(tileset_path, source)?.to_string(),
+        image: Handle::default(),
+        layout: Handle::default(),
+        collision_shapes,
+    })
+}
+
+fn parse_tile_shape(tileset_name: &str, tile_id: u32, object: Node) -> Option<TileShape> {
+    let x = object.attribute("x").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+    let y = object.attribute("y").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+
+    if let Some(polygon) = object.children().find(|n| n.has_tag_name("polygon")) {
+        let points = polygon
+            .attribute("points")
+            .unwrap_or_default()
+            .split_whitespace()
+            .filter_map(|point_str| {
+                let mut coords = point_str.split(',');
+                let dx = coords.next()?.parse::<f32>().ok()?;
+                let dy = coords.next()?.parse::<f32>().ok()?;
+                Some(Vec2::new(x + dx, y + dy))
+            })
+            .collect();
+        return Some(TileShape::Polygon(points));
+    }
+
+    let width = object.attribute("width").and_then(|s| s.parse::<f32>().ok());
+    let height = object.attribute("height").and_then(|s| s.parse::<f32>().ok());
+    if let (Some(width), Some(height)) = (width, height) {
+        // Custom properties don't make it any other shape
+        if object.children().all(|n| !n.is_element() || n.has_tag_name("properties")) {
+            return Some(TileShape::Rectangle(Rect::new(x, y, x + width, y + height)));
+        }
+    }
+
+    warn!("Unsupported collision shape on tile {} of tileset {}", tile_id, tileset_name);
+    None
+}
+
+// Tiles that fit along one side of a tileset image, None when the sizes don't add up
+fn atlas_cells(image_size: u32, tile_size: u32, margin: u32, spacing: u32) -> Option<u32> {
+    image_size
+        .checked_sub(margin)?
+        .checked_add(spacing)?
+        .checked_div(tile_size.checked_add(spacing)?)
+}
+
+/// Decodes a `<layer>` element into tiles, positioned with the same centering offset as objects.
+/// Collision shapes of the tiles are added to `static_shapes`, with fully solid tiles merged into
+/// as few rectangles as possible.
+pub(super) fn parse_tile_layer(
+    layer: Node,
+    tilesets: &[TmxTileset],
+    grid_size: Vec2,
+    center_offset: Vec2,
+    static_shapes: &mut Vec<StaticShape>,
+) -> Result<TmxTileLayer, TmxLevelLoaderError> {
+    let name = layer.attribute("name").unwrap_or_default().to_string();
+    let width: u32 = parse_attribute(layer, "width")?;
+    let offset = Vec2::new(
+        parse_attribute(layer, "offsetx").unwrap_or(0.0),
+        parse_attribute(layer, "offsety").unwrap_or(0.0),
+    );
+
+    let Some(data) = layer.children().find(|n| n.has_tag_name("data")) else {
+        return Err(TmxLevelLoaderError::InvalidData(format!("Layer {} has no data", name)));
+    };
+
+    // Infinite maps split the layer into chunks, each with its own origin
+    let mut cells = Vec::new();
+    let chunks: Vec<Node> = data.children().filter(|n| n.has_tag_name("chunk")).collect();
+    if chunks.is_empty() {
+        if width == 0 {
+            return Err(TmxLevelLoaderError::InvalidData(format!("Layer {} has no width", name)));
+        }
+        cells.extend(
+            decode_tile_data(data)?
+                .into_iter()
+                .enumerate()
+                .map(|(i, gid)| (UVec2::new(i as u32 % width, i as u32 / width).as_ivec2(), gid)),
+        );
+    } else {
+        for chunk in chunks {
+            let origin = IVec2::new(parse_attribute(chunk, "x")?, parse_attribute(chunk, "y")?);
+            let chunk_width: u32 = parse_attribute(chunk, "width")?;
+            if chunk_width == 0 {
+                return Err(TmxLevelLoaderError::InvalidData(format!(
+                    "Layer {} has a chunk without a width",
+                    name
+                )));
+            }
+            cells.extend(decode_tile_data(chunk)?.into_iter().enumerate().map(|(i, gid)| {
+                (origin + UVec2::new(i as u32 % chunk_width, i as u32 / chunk_width).as_ivec2(), gid)
+            }));
+        }
+    }
+
+    let mut tiles = Vec::new();
+    let mut solid_cells = HashSet::new();
+    for (cell, raw_gid) in cells {
+        let gid = raw_gid & GID_MASK;
+        if gid == 0 {
+            continue; // Empty cell
+        }
+
+        let Some(tileset_index) = tilesets.iter().rposition(|tileset| tileset.first_gid <= gid) else {
+            warn!("Layer {} references unknown tile gid {}", name, gid);
+            continue;
+        };
+        let tileset = &tilesets[tileset_index];
+
+        // Tiles are anchored to the bottom-left corner of their cell, so bigger tiles grow up and right
+        let tile_size = tileset.tile_size.as_vec2();
+        let cell_bottom_left = Vec2::new(cell.x as f32 * grid_size.x, (cell.y + 1) as f32 * grid_size.y);
+        let center = offset + cell_bottom_left + Vec2::new(tile_size.x, -tile_size.y) / 2.0;
+        let (flip_x, rotation) = tile_orientation(raw_gid);
+
+        let tile = TmxTile {
+            // Invert Y coordinate and apply centering offset
+            position: Vec2::new(center.x, -center.y) - center_offset,
+            tileset: tileset_index,
+            index: gid - tileset.first_gid,
+            flip_x,
+            rotation,
+        };
+
+        if tileset.is_solid(tile.index, grid_size) {
+            solid_cells.insert(cell);
+        } else if let Some(shapes) = tileset.collision_shapes.get(&tile.index) {
+            static_shapes.extend(shapes.iter().map(|shape| tile_shape_to_world(shape, &tile, tile_size)));
+        }
+
+        tiles.push(tile);
+    }
+
+    for (min, max) in merge_solid_cells(&solid_cells) {
+        // Cell corners in Tiled pixel space
+        let top_left = offset + min.as_vec2() * grid_size;
+        let bottom_right = offset + max.as_vec2() * grid_size;
+        let center = (top_left + bottom_right) / 2.0;
+        static_shapes.push(StaticShape::Rectangle {
+            center: Vec2::new(center.x, -center.y) - center_offset,
+            size: bottom_right - top_left,
+            rotation: 0.0,
+        });
+    }
+
+    Ok(TmxTileLayer {
+        name,
+        opacity: parse_attribute(layer, "opacity").unwrap_or(1.0),
+        tiles,
+    })
+}
+
+// Moves a tile-local shape to the world, applying the same flip and rotation as the tile sprite
+fn tile_shape_to_world(shape: &TileShape, tile: &TmxTile, tile_size: Vec2) -> StaticShape {
+    let to_world = |point: Vec2| {
+        // Relative to the tile center, Y-up
+        let mut local = Vec2::new(point.x - tile_size.x / 2.0, tile_size.y / 2.0 - point.y);
+        if tile.flip_x {
+            local.x = -local.x;
+        }
+        tile.position + Vec2::from_angle(tile.rotation).rotate(local)
+    };
+
+    match shape {
+        TileShape::Rectangle(rect) => StaticShape::Rectangle {
+            center: to_world(rect.center()),
+            size: rect.size(),
+            rotation: tile.rotation,
+        },
+        TileShape::Polygon(points) => {
+            let mut points: Vec<Vec2> = points.iter().map(|p| to_world(*p)).collect();
+            // The Y-flip reverses the winding order, and flipping the tile reverses it again
+            if !tile.flip_x {
+                points.reverse();
+            }
+            StaticShape::Polygon(points)
+        }
+    }
+}
+
+// Greedily covers the solid cells with rectangles: grow each rectangle right as far as possible,
+// then down as long as the whole row below is solid too. Returns (min, max) cell corners.
+fn merge_solid_cells(solid_cells: &HashSet<IVec2>) -> Vec<(IVec2, IVec2)> {
+    let mut remaining: Vec<IVec2> = solid_cells.iter().copied().collect();
+    remaining.sort_by_key(|cell| (cell.y, cell.x));
+
+    let mut visited = HashSet::new();
+    let mut rectangles = Vec::new();
+    for start in remaining {
+        if visited.contains(&start) {
+            continue;
+        }
+
+        let is_free = |cell: IVec2, visited: &HashSet<IVec2>| solid_cells.contains(&cell) && !visited.contains(&cell);
+
+        let mut end_x = start.x + 1;
+        while is_free(IVec2::new(end_x, start.y), &visited) {
+            end_x += 1;
+        }
+
+        let mut end_y = start.y + 1;
+        while (start.x..end_x).all(|x| is_free(IVec2::new(x, end_y), &visited)) {
+            end_y += 1;
+        }
+
+        for y in start.y..end_y {
+            for x in start.x..end_x {
+                visited.insert(IVec2::new(x, y));
+            }
+        }
+        rectangles.push((start, IVec2::new(end_x, end_y)));
+    }
+    rectangles
+}
+
+fn decode_tile_data(data: Node) -> Result<Vec<u32>, TmxLevelLoaderError> {
+    let text = data.text().unwrap_or_default().trim();
+
+    let encoding = data.attribute("encoding").or_else(|| data.parent().and_then(|p| p.attribute("encoding")));
+    let compression =
+        data.attribute("compression").or_else(|| data.parent().and_then(|p| p.attribute("compression")));
+
+    match encoding {
+        // Plain XML, one <tile gid="..."/> per cell
+        None => Ok(data
+            .children()
+            .filter(|n| n.has_tag_name("tile"))
+            .map(|tile| tile.attribute("gid").and_then(|s| s.parse().ok()).unwrap_or(0))
+            .collect()),
+        Some("csv") => text
+            .split(',')
+            .map(|s| {
+                s.trim()
+                    .parse::<u32>()
+                    .map_err(|e| TmxLevelLoaderError::InvalidData(format!("Invalid tile gid {}: {}", s.trim(), e)))
+            })
+            .collect(),
+        Some("base64") => {
+            let bytes = STANDARD.decode(text)?;
+            let bytes = match compression {
+                None => bytes,
+                Some("zlib") => {
+                    let mut decompressed = Vec::new();
+                    ZlibDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)?;
+                    decompressed
+                }
+                Some("gzip") => {
+                    let mut decompressed = Vec::new();
+                    GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)?;
+                    decompressed
+                }
+                Some(other) => {
+                    return Err(TmxLevelLoaderError::InvalidData(format!(
+                        "Unsupported layer compression: {}",
+                        other
+                    )));
+                }
+            };
+
+            if bytes.len() % 4 != 0 {
+                return Err(TmxLevelLoaderError::InvalidData(
+                    "Layer data length is not a multiple of 4 bytes".to_string(),
+                ));
+            }
+
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                .collect())
+        }
+        Some(other) => Err(TmxLevelLoaderError::InvalidData(format!("Unsupported layer encoding: {}", other))),
+    }
+}
+
+// Tiled flips the image diagonally first, then horizontally, then vertically, all in Y-down space.
+// A sprite can only flip and then rotate, so decompose the combined transform into those.
+fn tile_orientation(raw_gid: u32) -> (bool, f32) {
+    let mut matrix = Mat2::IDENTITY;
+    if raw_gid & FLIPPED_DIAGONALLY != 0 {
+        // A diagonal flip in Y-down space is an anti-diagonal flip in Y-up space
+        matrix = Mat2::from_cols(Vec2::new(0.0, -1.0), Vec2::new(-1.0, 0.0));
+    }
+    if raw_gid & FLIPPED_HORIZONTALLY != 0 {
+        matrix = Mat2::from_diagonal(Vec2::new(-1.0, 1.0)) * matrix;
+    }
+    if raw_gid & FLIPPED_VERTICALLY != 0 {
+        matrix = Mat2::from_diagonal(Vec2::new(1.0, -1.0)) * matrix;
+    }
+
+    let flip_x = matrix.determinant() < 0.0;
+    if flip_x {
+        matrix *= Mat2::from_diagonal(Vec2::new(-1.0, 1.0));
+    }
+    (flip_x, matrix.x_axis.y.atan2(matrix.x_axis.x))
+}
+
+pub(super) fn parse_attribute<T: std::str::FromStr>(node: Node, name: &str) -> Result<T, TmxLevelLoaderError> {
+    node.attribute(name)
+        .and_then(|s| s.parse::<T>().ok())
+        .ok_or_else(|| {
+            TmxLevelLoaderError::InvalidData(format!(
+                "Missing or invalid attribute {} on <{}>",
+                name,
+                node.tag_name().name()
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roxmltree::Document;
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    fn decode(xml: &str) -> Vec<u32> {
+        let doc = Document::parse(xml).unwrap();
+        decode_tile_data(doc.root_element()).unwrap()
+    }
+
+    // Gids 1, 2, an empty cell and a horizontally flipped 3, as Tiled writes them
+    const GIDS: [u32; 4] = [1, 2, 0, 0x8000_0003];
+
+    #[test]
+    fn decode_csv() {
+        assert_eq!(decode("<data encoding=\"csv\">\n1,2,0,\n2147483651\n</data>"), GIDS);
+    }
+
+    #[test]
+    fn decode_xml_tiles() {
+        assert_eq!(
+            decode(r#"<data><tile gid="1"/><tile gid="2"/><tile/><tile gid="2147483651"/></data>"#),
+            GIDS
+        );
+    }
+
+    #[test]
+    fn decode_base64() {
+        assert_eq!(decode(r#"<data encoding="base64">AQAAAAIAAAAAAAAAAwAAgA==</data>"#), GIDS);
+    }
+
+    #[test]
+    fn decode_base64_zlib() {
+        assert_eq!(
+            decode(r#"<data encoding="base64" compression="zlib">eJxjZGBgYGKAAGYGhgYAAMQAhw==</data>"#),
+            GIDS
+        );
+    }
+
+    #[test]
+    fn decode_base64_gzip() {
+        assert_eq!(
+            decode(r#"<data encoding="base64" compression="gzip">H4sIAAAAAAACA2NkYGBgYoAAZgaGBgCVaOVREAAAAA==</data>"#),
+            GIDS
+        );
+    }
+
+    #[test]
+    fn decode_rejects_unsupported_compression() {
+        let doc = Document::parse(r#"<data encoding="base64" compression="zstd">AQAAAA==</data>"#).unwrap();
+        assert!(matches!(
+            decode_tile_data(doc.root_element()),
+            Err(TmxLevelLoaderError::InvalidData(_))
+        ));
+    }
+
+    fn assert_orientation(raw_gid: u32, flip_x: bool, angle: f32) {
+        let (actual_flip_x, actual_angle) = tile_orientation(raw_gid);
+        assert_eq!(actual_flip_x, flip_x, "flip of gid {:#x}", raw_gid);
+        assert!((actual_angle - angle).abs() < 1e-5, "angle of gid {:#x} is {}", raw_gid, actual_angle);
+    }
+
+    #[test]
+    fn orientation_of_flips() {
+        assert_orientation(1, false, 0.0);
+        assert_orientation(FLIPPED_HORIZONTALLY | 1, true, 0.0);
+        // A vertical flip is a horizontal one turned upside down
+        assert_orientation(FLIPPED_VERTICALLY | 1, true, PI);
+        assert_orientation(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | 1, false, PI);
+    }
+
+    #[test]
+    fn orientation_of_rotations() {
+        // Tiled rotates a tile clockwise with a diagonal and a horizontal flip
+        assert_orientation(FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY | 1, false, -FRAC_PI_2);
+        assert_orientation(FLIPPED_DIAGONALLY | FLIPPED_VERTICALLY | 1, false, FRAC_PI_2);
+    }
+
+    fn cells(cells: &[(i32, i32)]) -> HashSet<IVec2> {
+        cells.iter().map(|&(x, y)| IVec2::new(x, y)).collect()
+    }
+
+    #[test]
+    fn solid_block_merges_into_one_rectangle() {
+        let block = cells(&[(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+        assert_eq!(merge_solid_cells(&block), vec![(IVec2::new(0, 0), IVec2::new(3, 2))]);
+    }
+
+    #[test]
+    fn l_shape_merges_rows_first() {
+        let l_shape = cells(&[(0, 0), (1, 0), (0, 1)]);
+        assert_eq!(
+            merge_solid_cells(&l_shape),
+            vec![(IVec2::new(0, 0), IVec2::new(2, 1)), (IVec2::new(0, 1), IVec2::new(1, 2))]
+        );
+    }
+
+    #[test]
+    fn merged_rectangles_cover_every_cell_once() {
+        let solid = cells(&[(-1, 0), (0, 0), (1, 0), (0, 1), (0, 2), (1, 2), (5, 5)]);
+        let mut covered = Vec::new();
+        for (min, max) in merge_solid_cells(&solid) {
+            for y in min.y..max.y {
+                for x in min.x..max.x {
+                    covered.push(IVec2::new(x, y));
+                }
+            }
+        }
+        assert_eq!(covered.len(), solid.len());
+        assert_eq!(covered.into_iter().collect::<HashSet<_>>(), solid);
+    }
+
+    fn tile_shape(xml: &str) -> Option<TileShape> {
+        let doc = Document::parse(xml).unwrap();
+        parse_tile_shape("test", 0, doc.root_element())
+    }
+
+    #[test]
+    fn rectangle_with_properties() {
+        let shape = tile_shape(
+            r#"<object id="1" x="0" y="16" width="32" height="16">
+                <properties><property name="restitution" type="float" value="0.2"/></properties>
+            </object>"#,
+        );
+        let Some(TileShape::Rectangle(rect)) = shape else {
+            panic!("expected a rectangle, got {:?}", shape);
+        };
+        assert_eq!(rect, Rect::new(0.0, 16.0, 32.0, 32.0));
+    }
+
+    #[test]
+    fn polygon_is_offset_by_the_object() {
+        let shape = tile_shape(r#"<object id="1" x="4" y="8"><polygon points="0,0 8,0 0,8"/></object>"#);
+        let Some(TileShape::Polygon(points)) = shape else {
+            panic!("expected a polygon, got {:?}", shape);
+        };
+        assert_eq!(points, vec![Vec2::new(4.0, 8.0), Vec2::new(12.0, 8.0), Vec2::new(4.0, 16.0)]);
+    }
+
+    #[test]
+    fn ellipses_are_unsupported() {
+        let shape = tile_shape(r#"<object id="1" x="0" y="0" width="8" height="8"><ellipse/></object>"#);
+        assert!(shape.is_none());
+    }
+
+    #[test]
+    fn atlas_cells_count_spacing_and_margin() {
+        assert_eq!(atlas_cells(64, 16, 0, 0), Some(4));
+        // 1px margin, then 16px tiles with 2px between them
+        assert_eq!(atlas_cells(1 + 16 + 2 + 16, 16, 1, 2), Some(2));
+    }
+
+    #[test]
+    fn atlas_cells_reject_impossible_sizes() {
+        assert_eq!(atlas_cells(4, 16, 8, 0), None);
+        assert_eq!(atlas_cells(64, 0, 0, 0), None);
+    }
+
+    #[test]
+    fn tileset_smaller_than_its_margin_is_invalid() {
+        let doc = Document::parse(
+            r#"<tileset name="tiny" tilewidth="16" tileheight="16" margin="8">
+                <image source="tiny.png" width="4" height="4"/>
+            </tileset>"#,
+        )
+        .unwrap();
+        let result = parse_tileset(1, doc.root_element(), &AssetPath::from("levels/test.tmx"));
+        assert!(matches!(result, Err(TmxLevelLoaderError::InvalidData(_))));
+    }
+
+    #[test]
+    fn layer_without_width_is_invalid() {
+        let doc = Document::parse(r#"<layer name="ground" width="0" height="1"><data encoding="csv">1</data></layer>"#)
+            .unwrap();
+        let result = parse_tile_layer(doc.root_element(), &[], Vec2::splat(16.0), Vec2::ZERO, &mut Vec::new());
+        assert!(matches!(result, Err(TmxLevelLoaderError::InvalidData(_))));
+    }
+
+    #[test]
+    fn chunk_without_width_is_invalid() {
+        let doc = Document::parse(
+            r#"<layer name="ground" width="16" height="16">
+                <data encoding="csv"><chunk x="0" y="0" width="0" height="1">1</chunk></data>
+            </layer>"#,
+        )
+        .unwrap();
+        let result = parse_tile_layer(doc.root_element(), &[], Vec2::splat(16.0), Vec2::ZERO, &mut Vec::new());
+        assert!(matches!(result, Err(TmxLevelLoaderError::InvalidData(_))));
+    }
+}
@@ -0,0 +1,397 @@
+use crate::in_game::levels::ball_types::BallParameters;
+use crate::in_game::levels::properties::{PhysicsMaterial, TiledProperties};
+use crate::in_game::levels::shapes::{parse_object_shape, StaticShape};
+use crate::in_game::levels::tiles::{
+    external_tileset_paths, parse_attribute, parse_tile_layer, parse_tileset, resolve_path, TmxTileLayer,
+    TmxTileset,
+};
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AssetPath, LoadContext, ReadAssetBytesError};
+use bevy::prelude::*;
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::fmt;
+
+/// A parsed Tiled map, ready to be spawned by the level loading systems.
+/// All positions are in world space, already Y-flipped and centered around the origin.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct TmxLevel {
+    pub static_bodies: Vec<StaticBody>,
+    pub ball_spawns: Vec<BallSpawn>,
+    pub player_spawns: Vec<Vec2>,
+    pub bounds: Rect,
+    /// Custom properties of the map itself
+    pub properties: TiledProperties,
+    pub tilesets: Vec<TmxTileset>,
+    /// Tile layers in drawing order, bottom first
+    pub tile_layers: Vec<TmxTileLayer>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StaticBody {
+    pub shape: StaticShape,
+    pub material: PhysicsMaterial,
+}
+
+#[derive(Debug, Clone)]
+pub struct BallSpawn {
+    pub position: Vec2,
+    pub parameters: BallParameters,
+    pub material: PhysicsMaterial,
+}
+
+#[derive(Default)]
+pub struct TmxLevelLoader;
+
+#[derive(Debug)]
+pub enum TmxLevelLoaderError {
+    Io(std::io::Error),
+    Utf8(std::str::Utf8Error),
+    Xml(roxmltree::Error),
+    Base64(base64::DecodeError),
+    ReadAssetBytes(ReadAssetBytesError),
+    InvalidData(String),
+}
+
+impl fmt::Display for TmxLevelLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Failed to read TMX file: {}", e),
+            Self::Utf8(e) => write!(f, "TMX file is not valid UTF-8: {}", e),
+            Self::Xml(e) => write!(f, "Failed to parse TMX file: {}", e),
+            Self::Base64(e) => write!(f, "Failed to decode base64 layer data: {}", e),
+            Self::ReadAssetBytes(e) => write!(f, "Failed to read external tileset: {}", e),
+            Self::InvalidData(message) => write!(f, "Invalid TMX data: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for TmxLevelLoaderError {}
+
+impl From<std::io::Error> for TmxLevelLoaderError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<std::str::Utf8Error> for TmxLevelLoaderError {
+    fn from(e: std::str::Utf8Error) -> Self {
+        Self::Utf8(e)
+    }
+}
+
+impl From<roxmltree::Error> for TmxLevelLoaderError {
+    fn from(e: roxmltree::Error) -> Self {
+        Self::Xml(e)
+    }
+}
+
+impl From<base64::DecodeError> for TmxLevelLoaderError {
+    fn from(e: base64::DecodeError) -> Self {
+        Self::Base64(e)
+    }
+}
+
+impl From<ReadAssetBytesError> for TmxLevelLoaderError {
+    fn from(e: ReadAssetBytesError) -> Self {
+        Self::ReadAssetBytes(e)
+    }
+}
+
+impl AssetLoader for TmxLevelLoader {
+    type Asset = TmxLevel;
+    type Settings = ();
+    type Error = TmxLevelLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<TmxLevel, TmxLevelLoaderError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let tmx_content = std::str::from_utf8(&bytes)?;
+        let map_path = load_context.asset_path().clone();
+
+        // Reading external tilesets through the load context makes them dependencies,
+        // so saving a .tsx file reloads the map too
+        let tileset_paths = {
+            let doc = Document::parse(tmx_content)?;
+            external_tileset_paths(&map_path, doc.root_element())?
+        };
+        let mut external_tilesets = HashMap::new();
+        for tileset_path in tileset_paths {
+            let tileset_bytes = load_context.read_asset_bytes(tileset_path.clone()).await?;
+            external_tilesets.insert(tileset_path.to_string(), String::from_utf8_lossy(&tileset_bytes).into_owned());
+        }
+
+        let mut level = parse_tmx(&map_path, tmx_content, &external_tilesets)?;
+
+        for (i, tileset) in level.tilesets.iter_mut().enumerate() {
+            tileset.image = load_context.load(tileset.image_path.clone());
+            tileset.layout = load_context.add_labeled_asset(
+                format!("tileset{}", i),
+                TextureAtlasLayout::from_grid(
+                    tileset.tile_size,
+                    tileset.columns,
+                    tileset.rows(),
+                    Some(UVec2::splat(tileset.spacing)),
+                    Some(UVec2::splat(tileset.margin)),
+                ),
+            );
+        }
+
+        Ok(level)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmx"]
+    }
+}
+
+// Helper struct to track level bounds
+struct LevelBounds {
+    min_x: f32,
+    max_x: f32,
+    min_y: f32,
+    max_y: f32,
+}
+
+impl LevelBounds {
+    fn new() -> Self {
+        Self {
+            min_x: f32::MAX,
+            max_x: f32::MIN,
+            min_y: f32::MAX,
+            max_y: f32::MIN,
+        }
+    }
+
+    fn update(&mut self, x: f32, y: f32) {
+        self.min_x = self.min_x.min(x);
+        self.max_x = self.max_x.max(x);
+        self.min_y = self.min_y.min(y);
+        self.max_y = self.max_y.max(y);
+    }
+
+    fn center(&self) -> Vec2 {
+        Vec2::new(
+            (self.min_x + self.max_x) / 2.0,
+            (self.min_y + self.max_y) / 2.0,
+        )
+    }
+
+    fn to_rect(&self, offset: Vec2) -> Rect {
+        if self.min_x > self.max_x {
+            return Rect::default();
+        }
+        Rect::new(
+            self.min_x - offset.x,
+            self.min_y - offset.y,
+            self.max_x - offset.x,
+            self.max_y - offset.y,
+        )
+    }
+}
+
+/// Parses the contents of a TMX file into a [`TmxLevel`]. External tilesets are looked up from
+/// `external_tilesets` by their resolved asset path. Image and atlas handles are left empty.
+pub fn parse_tmx(
+    map_path: &AssetPath,
+    tmx_content: &str,
+    external_tilesets: &HashMap<String, String>,
+) -> Result<TmxLevel, TmxLevelLoaderError> {
+    let doc = Document::parse(tmx_content)?;
+    let map = doc.root_element();
+
+    let grid_size = Vec2::new(
+        parse_attribute(map, "tilewidth").unwrap_or(0.0),
+        parse_attribute(map, "tileheight").unwrap_or(0.0),
+    );
+
+    // Calculate level bounds first, tile layers count as much as objects
+    let mut bounds = LevelBounds::new();
+    for object_group in map_object_groups(&doc) {
+        calculate_layer_bounds(&mut bounds, object_group);
+    }
+    for layer in visible_tile_layers(&doc) {
+        calculate_tile_layer_bounds(&mut bounds, layer, grid_size);
+    }
+
+    // Calculate the offset to center the level
+    let center_offset = bounds.center();
+
+    let mut level = TmxLevel {
+        static_bodies: Vec::new(),
+        ball_spawns: Vec::new(),
+        player_spawns: Vec::new(),
+        bounds: bounds.to_rect(center_offset),
+        properties: TiledProperties::parse(map),
+        tilesets: Vec::new(),
+        tile_layers: Vec::new(),
+    };
+
+    for tileset in map.children().filter(|n| n.has_tag_name("tileset")) {
+        let first_gid = parse_attribute(tileset, "firstgid")?;
+        let parsed = match tileset.attribute("source") {
+            Some(source) => {
+                let tileset_path = resolve_path(map_path, source)?;
+                let Some(tsx_content) = external_tilesets.get(&tileset_path.to_string()) else {
+                    return Err(TmxLevelLoaderError::InvalidData(format!(
+                        "External tileset {} was not loaded",
+                        tileset_path
+                    )));
+                };
+                let tsx = Document::parse(tsx_content)?;
+                parse_tileset(first_gid, tsx.root_element(), &tileset_path)?
+            }
+            None => parse_tileset(first_gid, tileset, map_path)?,
+        };
+        level.tilesets.push(parsed);
+    }
+
+    for layer in visible_tile_layers(&doc) {
+        let mut tile_shapes = Vec::new();
+        let tile_layer = parse_tile_layer(layer, &level.tilesets, grid_size, center_offset, &mut tile_shapes)?;
+        let material = PhysicsMaterial::from_properties(&TiledProperties::parse(layer));
+        level.static_bodies.extend(tile_shapes.into_iter().map(|shape| StaticBody {
+            shape,
+            material: material.clone(),
+        }));
+        if !tile_layer.tiles.is_empty() {
+            level.tile_layers.push(tile_layer);
+        }
+    }
+
+    // Process object layers with centering offset
+    for object_group in map_object_groups(&doc) {
+        match object_group.attribute("name") {
+            Some("static") => process_static_geometry(&mut level, object_group, center_offset),
+            Some("balls") => process_balls(&mut level, object_group, center_offset),
+            Some("player") => level.player_spawns.extend(object_positions(object_group, center_offset)),
+            _ => continue,
+        }
+    }
+
+    Ok(level)
+}
+
+// Object layers of the map itself, skipping the collision shapes of tiles in inline tilesets
+pub(super) fn map_object_groups<'a, 'input>(doc: &'a Document<'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    doc.descendants()
+        .filter(|n| n.has_tag_name("objectgroup"))
+        .filter(|n| !n.ancestors().any(|a| a.has_tag_name("tileset")))
+}
+
+// Hidden layers are left out of the level entirely
+fn visible_tile_layers<'a, 'input>(doc: &'a Document<'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    doc.descendants()
+        .filter(|n| n.has_tag_name("layer"))
+        .filter(|n| n.attribute("visible") != Some("0"))
+}
+
+// The whole grid of a tile layer, empty cells included, like Tiled shows it
+fn calculate_tile_layer_bounds(bounds: &mut LevelBounds, layer: Node, grid_size: Vec2) {
+    let size = Vec2::new(
+        parse_attribute(layer, "width").unwrap_or(0.0),
+        parse_attribute(layer, "height").unwrap_or(0.0),
+    );
+    let offset = Vec2::new(
+        parse_attribute(layer, "offsetx").unwrap_or(0.0),
+        parse_attribute(layer, "offsety").unwrap_or(0.0),
+    );
+    // Layers of infinite maps start wherever their first chunk is
+    let start = Vec2::new(
+        parse_attribute(layer, "startx").unwrap_or(0.0),
+        parse_attribute(layer, "starty").unwrap_or(0.0),
+    );
+
+    let top_left = offset + start * grid_size;
+    let bottom_right = top_left + size * grid_size;
+    // Invert Y coordinate
+    bounds.update(top_left.x, -top_left.y);
+    bounds.update(bottom_right.x, -bottom_right.y);
+}
+
+fn calculate_layer_bounds(bounds: &mut LevelBounds, object_group: Node) {
+    for object in object_group.children().filter(|n| n.has_tag_name("object")) {
+        let x = object.attribute("x").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+        let y = object.attribute("y").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+        let y = -y; // Invert Y coordinate
+
+        bounds.update(x, y);
+
+        // For shaped objects, also check their outline
+        if let Ok(shape) = parse_object_shape(object, Vec2::ZERO) {
+            for point in shape.outline() {
+                bounds.update(point.x, point.y);
+            }
+        }
+    }
+}
+
+fn process_static_geometry(level: &mut TmxLevel, object_group: Node, center_offset: Vec2) {
+    let layer_properties = TiledProperties::parse(object_group);
+    for object in object_group.children().filter(|n| n.has_tag_name("object")) {
+        let properties = layer_properties.merged(&TiledProperties::parse(object));
+        match parse_object_shape(object, center_offset) {
+            Ok(shape) => level.static_bodies.push(StaticBody {
+                shape,
+                material: PhysicsMaterial::from_properties(&properties),
+            }),
+            Err(message) => warn!(
+                "Skipping static object {}: {}",
+                object.attribute("id").unwrap_or("?"),
+                message
+            ),
+        }
+    }
+}
+
+fn process_balls(level: &mut TmxLevel, object_group: Node, center_offset: Vec2) {
+    let layer_properties = TiledProperties::parse(object_group);
+    for object in object_group.children().filter(|n| n.has_tag_name("object")) {
+        let properties = layer_properties.merged(&TiledProperties::parse(object));
+        level.ball_spawns.push(BallSpawn {
+            position: object_position(object, center_offset),
+            parameters: BallParameters::from_object(object, &properties),
+            material: PhysicsMaterial::from_properties(&properties),
+        });
+    }
+}
+
+fn object_position(object: Node, center_offset: Vec2) -> Vec2 {
+    let x = object.attribute("x").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+    let y = object.attribute("y").and_then(|s| s.parse::<f32>().ok()).unwrap_or(0.0);
+    // Invert Y coordinate and apply centering offset
+    Vec2::new(x, -y) - center_offset
+}
+
+fn object_positions(object_group: Node, center_offset: Vec2) -> Vec<Vec2> {
+    object_group
+        .children()
+        .filter(|n| n.has_tag_name("object"))
+        .map(|object| object_position(object, center_offset))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_layers_count_for_the_bounds() {
+        let tmx = r#"<map version="1.10" orientation="orthogonal" width="4" height="2" tilewidth="32" tileheight="32">
+ <layer id="1" name="ground" width="4" height="2">
+  <data encoding="csv">0,0,0,0,0,0,0,0</data>
+ </layer>
+ <objectgroup id="2" name="player">
+  <object id="1" x="16" y="16"/>
+ </objectgroup>
+</map>"#;
+        let level = parse_tmx(&AssetPath::from("levels/test.tmx"), tmx, &HashMap::new()).unwrap();
+        assert_eq!(level.bounds, Rect::new(-64.0, -32.0, 64.0, 32.0));
+        assert_eq!(level.player_spawns, vec![Vec2::new(-48.0, 16.0)]);
+    }
+}
@@ -0,0 +1,493 @@
+//! Checks levels for mistakes the loader would skip over with a warning, or not notice at all.
+//!
+//! [`validate_tmx`] finds missing or extra player spawns, balls overlapping the level or each other,
+//! static objects that can't be used, unknown layers and properties, and values that don't parse or
+//! are out of range. The `splittin-lint` binary runs it on every level.
+
+use crate::in_game::levels::ball_types::{BallParameters, parse_tiled_color};
+use crate::in_game::levels::properties::{CollisionLayerProperty, PhysicsMaterial, TiledProperties};
+use crate::in_game::levels::shapes::{StaticShape, parse_object_shape};
+use crate::in_game::levels::tiles::external_tileset_paths;
+use crate::in_game::levels::tmx::{BallSpawn, StaticBody, map_object_groups, parse_tmx};
+use crate::in_game::levels::{polygon_collider, static_collider};
+use crate::in_game::outcome::{LevelGoal, LevelGoals};
+use avian2d::parry::math::{Isometry, Vector};
+use avian2d::parry::query::intersection_test;
+use avian2d::parry::shape::Ball;
+use bevy::asset::AssetPath;
+use bevy::prelude::*;
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// Shapes may touch by this much before they count as overlapping
+const OVERLAP_TOLERANCE: f32 = 0.5;
+const OBJECT_LAYERS: [&str; 3] = ["static", "balls", "player"];
+const NUMERIC_ATTRIBUTES: [&str; 5] = ["x", "y", "width", "height", "rotation"];
+
+/// Something wrong with a level
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelProblem {
+    /// Id of the Tiled object the problem is on, `None` for the map and its layers
+    pub object_id: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for LevelProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.object_id {
+            Some(id) => write!(f, "object {}: {}", id, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl LevelProblem {
+    // Problems of layers name the layer, objects are found by their id
+    fn at(node: Node, message: impl Into<String>) -> Self {
+        let message = message.into();
+        match node.tag_name().name() {
+            "object" => Self {
+                object_id: Some(node.attribute("id").unwrap_or("?").to_string()),
+                message,
+            },
+            "layer" | "objectgroup" => Self {
+                object_id: None,
+                message: format!("layer {}: {}", node.attribute("name").unwrap_or("?"), message),
+            },
+            _ => Self { object_id: None, message },
+        }
+    }
+}
+
+/// What values a custom property takes
+#[derive(Debug, Clone, Copy)]
+enum PropertyKind {
+    Float { min: f32, max: f32 },
+    Int { min: u64, max: u64 },
+    Bool,
+    Color,
+    OneOf(&'static [&'static str]),
+}
+
+impl PropertyKind {
+    const COUNT: Self = Self::Int { min: 0, max: u32::MAX as u64 };
+    const POSITIVE_COUNT: Self = Self::Int { min: 1, max: u32::MAX as u64 };
+    const DISTANCE: Self = Self::Float { min: 0.0, max: f32::INFINITY };
+
+    /// What's wrong with the value, if anything
+    fn check(&self, value: &str) -> Option<String> {
+        let valid = match self {
+            Self::Float { min, max } => value.parse::<f32>().is_ok_and(|value| (*min..=*max).contains(&value)),
+            Self::Int { min, max } => value.parse::<u64>().is_ok_and(|value| (*min..=*max).contains(&value)),
+            Self::Bool => value.parse::<bool>().is_ok(),
+            Self::Color => parse_tiled_color(value).is_some(),
+            Self::OneOf(options) => options.contains(&value),
+        };
+        if valid {
+            return None;
+        }
+
+        Some(match self {
+            Self::Float { min, max } if max.is_infinite() => format!("expected a number of at least {}", min),
+            Self::Float { min, max } => format!("expected a number from {} to {}", min, max),
+            Self::Int { min, max } if *max >= u32::MAX as u64 => format!("expected a whole number of at least {}", min),
+            Self::Int { min, max } => format!("expected a whole number from {} to {}", min, max),
+            Self::Bool => "expected true or false".to_string(),
+            Self::Color => "expected a color like #RRGGBB".to_string(),
+            Self::OneOf(options) => format!("expected one of {}", options.join(", ")),
+        })
+    }
+}
+
+// The properties the game reads, see the docs of `properties` and `ball_types`
+const MAP_PROPERTIES: &[(&str, PropertyKind)] = &[
+    ("max_chain_depth", PropertyKind::COUNT),
+    ("goal", PropertyKind::OneOf(&["pop_all", "pop_count", "one_shot"])),
+    ("goal_count", PropertyKind::POSITIVE_COUNT),
+    ("ammo", PropertyKind::POSITIVE_COUNT),
+    ("aim_preview", PropertyKind::DISTANCE),
+    ("aim_preview_bounces", PropertyKind::COUNT),
+    ("seed", PropertyKind::Int { min: 0, max: u64::MAX }),
+    ("shoot_mode", PropertyKind::OneOf(&["stepped", "charge", "swing"])),
+];
+
+const PHYSICS_PROPERTIES: &[(&str, PropertyKind)] = &[
+    ("restitution", PropertyKind::Float { min: 0.0, max: 1.0 }),
+    ("friction", PropertyKind::DISTANCE),
+    ("mass", PropertyKind::Float { min: 0.001, max: f32::INFINITY }),
+    ("sensor", PropertyKind::Bool),
+    ("collision_layer", PropertyKind::OneOf(&["all", "balls", "ammo"])),
+];
+
+const BALL_PROPERTIES: &[(&str, PropertyKind)] = &[
+    ("radius", PropertyKind::Float { min: 1.0, max: f32::INFINITY }),
+    ("static", PropertyKind::Bool),
+    ("tier", PropertyKind::COUNT),
+    ("split_count", PropertyKind::COUNT),
+    ("split_spread", PropertyKind::Float { min: 0.0, max: 360.0 }),
+    ("split_speed", PropertyKind::DISTANCE),
+    ("split_speed_reference", PropertyKind::Float { min: 1.0, max: f32::INFINITY }),
+    ("split_speed_min", PropertyKind::DISTANCE),
+    ("split_speed_max", PropertyKind::DISTANCE),
+    ("split_speed_exponent", PropertyKind::Float { min: 0.01, max: f32::INFINITY }),
+    ("split_inherit_momentum", PropertyKind::Bool),
+    ("split_static_children", PropertyKind::Bool),
+    ("color", PropertyKind::Color),
+    ("score", PropertyKind::COUNT),
+    ("hit_points", PropertyKind::POSITIVE_COUNT),
+];
+
+/// Reads a level from the assets folder and checks it. External tilesets are read from the assets folder too.
+pub fn validate_tmx_file(assets_dir: &Path, asset_path: &str) -> std::io::Result<Vec<LevelProblem>> {
+    let map_path = AssetPath::from(asset_path);
+    let tmx_content = fs::read_to_string(assets_dir.join(asset_path))?;
+
+    // Tilesets that can't be read are reported by the parser
+    let mut external_tilesets = HashMap::new();
+    let tileset_paths = Document::parse(&tmx_content)
+        .ok()
+        .and_then(|doc| external_tileset_paths(&map_path, doc.root_element()).ok())
+        .unwrap_or_default();
+    for tileset_path in tileset_paths {
+        if let Ok(tsx_content) = fs::read_to_string(assets_dir.join(tileset_path.path())) {
+            external_tilesets.insert(tileset_path.to_string(), tsx_content);
+        }
+    }
+
+    Ok(validate_tmx(&map_path, &tmx_content, &external_tilesets))
+}
+
+/// Checks the contents of a TMX file, with the same arguments as [`parse_tmx`]. No problems means the level
+/// loads exactly as it was drawn.
+pub fn validate_tmx(
+    map_path: &AssetPath,
+    tmx_content: &str,
+    external_tilesets: &HashMap<String, String>,
+) -> Vec<LevelProblem> {
+    let level = match parse_tmx(map_path, tmx_content, external_tilesets) {
+        Ok(level) => level,
+        // Nothing else can be checked in a map that doesn't load
+        Err(e) => return vec![LevelProblem { object_id: None, message: e.to_string() }],
+    };
+    let Ok(doc) = Document::parse(tmx_content) else {
+        return Vec::new();
+    };
+    let map = doc.root_element();
+    let mut problems = Vec::new();
+
+    for attribute in ["tilewidth", "tileheight"] {
+        if map.attribute(attribute).and_then(|value| value.parse::<f32>().ok()).is_none() {
+            problems.push(LevelProblem::at(map, format!("the map has no valid {} attribute", attribute)));
+        }
+    }
+    check_properties(&mut problems, map, &[MAP_PROPERTIES]);
+    for layer in doc.descendants().filter(|n| n.has_tag_name("layer")) {
+        check_properties(&mut problems, layer, &[PHYSICS_PROPERTIES]);
+    }
+
+    let mut ball_objects = Vec::new();
+    let mut player_objects = Vec::new();
+    let mut has_player_layer = false;
+    for object_group in map_object_groups(&doc) {
+        let objects = object_group.children().filter(|n| n.has_tag_name("object"));
+        match object_group.attribute("name") {
+            Some("static") => {
+                check_properties(&mut problems, object_group, &[PHYSICS_PROPERTIES]);
+                for object in objects {
+                    check_object(&mut problems, object, &[PHYSICS_PROPERTIES]);
+                    check_static_object(&mut problems, object);
+                }
+            }
+            Some("balls") => {
+                check_properties(&mut problems, object_group, &[BALL_PROPERTIES, PHYSICS_PROPERTIES]);
+                for object in objects {
+                    check_object(&mut problems, object, &[BALL_PROPERTIES, PHYSICS_PROPERTIES]);
+                    let kind = object.attribute("class").or_else(|| object.attribute("type")).unwrap_or_default();
+                    if BallParameters::preset(kind).is_none() {
+                        problems.push(LevelProblem::at(object, format!("unknown ball type {}", kind)));
+                    }
+                    ball_objects.push(object);
+                }
+            }
+            Some("player") => {
+                has_player_layer = true;
+                check_properties(&mut problems, object_group, &[]);
+                for object in objects {
+                    check_object(&mut problems, object, &[]);
+                    player_objects.push(object);
+                }
+            }
+            _ => problems.push(LevelProblem::at(
+                object_group,
+                format!("unknown object layer, expected one of {}", OBJECT_LAYERS.join(", ")),
+            )),
+        }
+    }
+
+    if !has_player_layer {
+        problems.push(LevelProblem::at(map, "the map has no player layer"));
+    } else if player_objects.is_empty() {
+        problems.push(LevelProblem::at(map, "the player layer has no player spawn"));
+    }
+    for object in player_objects.iter().skip(1) {
+        problems.push(LevelProblem::at(*object, "a level has a single player, this is another one"));
+    }
+
+    // Nothing to pop means nothing to win, the player can only shoot until the ammo runs out
+    if LevelGoals::from_properties(&level.properties).goal == LevelGoal::PopAll && level.ball_spawns.is_empty() {
+        problems.push(LevelProblem::at(map, "the goal is to pop all balls, but the level has none"));
+    }
+
+    // The parser spawns one ball per object of the balls layers, in the same order
+    let balls: Vec<_> = ball_objects.iter().zip(&level.ball_spawns).collect();
+    for (i, (object, ball)) in balls.iter().enumerate() {
+        if ball.material.sensor {
+            continue;
+        }
+        for (other_object, other) in balls.iter().skip(i + 1) {
+            let distance = ball.position.distance(other.position);
+            if !other.material.sensor && distance + OVERLAP_TOLERANCE < ball.parameters.radius + other.parameters.radius {
+                problems.push(LevelProblem::at(
+                    **object,
+                    format!("ball overlaps ball {}", other_object.attribute("id").unwrap_or("?")),
+                ));
+            }
+        }
+        if overlaps_geometry(ball, &level.static_bodies) {
+            problems.push(LevelProblem::at(**object, "ball overlaps the level geometry"));
+        }
+    }
+
+    problems
+}
+
+fn check_properties(problems: &mut Vec<LevelProblem>, node: Node, known: &[&[(&str, PropertyKind)]]) {
+    let properties = TiledProperties::parse(node);
+    let mut names: Vec<_> = properties.names().collect();
+    // Properties are kept in a map, sorting keeps the report the same from run to run
+    names.sort_unstable();
+
+    for name in names {
+        let Some(kind) = known.iter().flat_map(|list| list.iter()).find(|(known_name, _)| *known_name == name) else {
+            problems.push(LevelProblem::at(node, format!("unknown property {}", name)));
+            continue;
+        };
+        let value = properties.get_str(name).unwrap_or_default();
+        if let Some(expected) = kind.1.check(value) {
+            problems.push(LevelProblem::at(node, format!("invalid {} {}, {}", name, value, expected)));
+        }
+    }
+}
+
+// Attributes that don't parse are read as 0 by the loader
+fn check_object(problems: &mut Vec<LevelProblem>, object: Node, known: &[&[(&str, PropertyKind)]]) {
+    for attribute in NUMERIC_ATTRIBUTES {
+        let Some(value) = object.attribute(attribute) else {
+            continue;
+        };
+        if value.parse::<f32>().is_err() {
+            problems.push(LevelProblem::at(object, format!("invalid {} attribute {}", attribute, value)));
+        }
+    }
+    check_properties(problems, object, known);
+}
+
+fn check_static_object(problems: &mut Vec<LevelProblem>, object: Node) {
+    match parse_object_shape(object, Vec2::ZERO) {
+        Err(message) => problems.push(LevelProblem::at(object, format!("{}, the object is skipped", message))),
+        Ok(StaticShape::Polygon(points)) => {
+            if points.len() < 3 {
+                problems.push(LevelProblem::at(object, format!("polygon has only {} points", points.len())));
+            } else if crosses_itself(&points) {
+                problems.push(LevelProblem::at(object, "polygon crosses itself"));
+            } else if polygon_collider(&points).is_none() {
+                problems.push(LevelProblem::at(object, "polygon can't be triangulated, the object is skipped"));
+            }
+        }
+        Ok(_) => {}
+    }
+}
+
+// Whether any two edges that don't share a corner cross
+fn crosses_itself(points: &[Vec2]) -> bool {
+    let count = points.len();
+    let edge = |i: usize| (points[i], points[(i + 1) % count]);
+    (0..count).any(|i| {
+        (i + 2..count)
+            // The last edge shares a corner with the first one
+            .filter(|&j| !(i == 0 && j == count - 1))
+            .any(|j| segments_cross(edge(i), edge(j)))
+    })
+}
+
+fn segments_cross((a, b): (Vec2, Vec2), (c, d): (Vec2, Vec2)) -> bool {
+    let side = |from: Vec2, to: Vec2, point: Vec2| (to - from).perp_dot(point - from);
+    side(a, b, c) * side(a, b, d) < 0.0 && side(c, d, a) * side(c, d, b) < 0.0
+}
+
+fn overlaps_geometry(ball: &BallSpawn, bodies: &[StaticBody]) -> bool {
+    let shape = Ball::new((ball.parameters.radius - OVERLAP_TOLERANCE).max(OVERLAP_TOLERANCE));
+    let position = Isometry::translation(ball.position.x, ball.position.y);
+
+    bodies
+        .iter()
+        .filter(|body| blocks_balls(&body.material))
+        .filter_map(|body| static_collider(&body.shape))
+        .any(|(collider, transform)| {
+            let body_position = Isometry::new(
+                Vector::new(transform.translation.x, transform.translation.y),
+                transform.rotation.to_euler(EulerRot::XYZ).2,
+            );
+            // Shapes parry can't test against, like ellipses, are left out
+            intersection_test(&position, &shape, &body_position, collider.shape().as_ref()).unwrap_or(false)
+        })
+}
+
+fn blocks_balls(material: &PhysicsMaterial) -> bool {
+    !material.sensor && material.collision_layer != Some(CollisionLayerProperty::Ammo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A floor, a player and a ball well above the floor
+    const VALID_LEVEL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="20" height="10" tilewidth="32" tileheight="32">
+ <objectgroup id="1" name="static">
+  <object id="1" x="0" y="200">
+   <polygon points="0,0 400,0 400,20 0,20"/>
+  </object>
+ </objectgroup>
+ <objectgroup id="2" name="player">
+  <object id="2" x="50" y="100"/>
+ </objectgroup>
+ <objectgroup id="3" name="balls">
+  <object id="3" x="200" y="50"/>
+ </objectgroup>
+</map>"#;
+
+    fn validate(tmx_content: &str) -> Vec<LevelProblem> {
+        validate_tmx(&AssetPath::from("levels/test.tmx"), tmx_content, &HashMap::new())
+    }
+
+    fn with_balls(balls: &str) -> String {
+        VALID_LEVEL.replace(r#"<object id="3" x="200" y="50"/>"#, balls)
+    }
+
+    fn messages(problems: &[LevelProblem]) -> Vec<(Option<&str>, &str)> {
+        problems
+            .iter()
+            .map(|problem| (problem.object_id.as_deref(), problem.message.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn valid_level() {
+        assert_eq!(validate(VALID_LEVEL), Vec::new());
+    }
+
+    #[test]
+    fn ball_inside_the_floor() {
+        let problems = validate(&with_balls(r#"<object id="3" x="200" y="210"/>"#));
+        assert_eq!(messages(&problems), [(Some("3"), "ball overlaps the level geometry")]);
+    }
+
+    #[test]
+    fn balls_on_top_of_each_other() {
+        let problems = validate(&with_balls(r#"<object id="3" x="200" y="50"/><object id="4" x="202" y="50"/>"#));
+        assert_eq!(messages(&problems), [(Some("3"), "ball overlaps ball 4")]);
+    }
+
+    #[test]
+    fn unknown_ball_type_and_property() {
+        let problems = validate(&with_balls(
+            r#"<object id="3" class="no_such_ball" x="200" y="50">
+   <properties>
+    <property name="bounciness" type="float" value="2"/>
+   </properties>
+  </object>"#,
+        ));
+        let messages = messages(&problems);
+        assert!(messages.contains(&(Some("3"), "unknown ball type no_such_ball")), "{:?}", messages);
+        assert!(messages.contains(&(Some("3"), "unknown property bounciness")), "{:?}", messages);
+    }
+
+    #[test]
+    fn property_out_of_range() {
+        let level = VALID_LEVEL.replace(
+            r#"name="static">"#,
+            r#"name="static">
+  <properties>
+   <property name="restitution" type="float" value="1.5"/>
+  </properties>"#,
+        );
+        let problems = validate(&level);
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert_eq!(problems[0].object_id, None);
+        assert!(problems[0].message.starts_with("layer static: invalid restitution 1.5"), "{}", problems[0].message);
+    }
+
+    #[test]
+    fn polygon_crossing_itself() {
+        let level = VALID_LEVEL.replace("0,0 400,0 400,20 0,20", "0,0 400,20 400,0 0,20");
+        assert_eq!(messages(&validate(&level)), [(Some("1"), "polygon crosses itself")]);
+    }
+
+    #[test]
+    fn level_without_player() {
+        let level = VALID_LEVEL.replace(r#"<object id="2" x="50" y="100"/>"#, "");
+        assert_eq!(messages(&validate(&level)), [(None, "the player layer has no player spawn")]);
+    }
+
+    #[test]
+    fn crossing_edges() {
+        let bowtie = [Vec2::ZERO, Vec2::new(10.0, 10.0), Vec2::new(10.0, 0.0), Vec2::new(0.0, 10.0)];
+        assert!(crosses_itself(&bowtie));
+    }
+
+    #[test]
+    fn shared_corners_and_concave_polygons_dont_cross() {
+        let square = [Vec2::ZERO, Vec2::new(10.0, 0.0), Vec2::new(10.0, 10.0), Vec2::new(0.0, 10.0)];
+        assert!(!crosses_itself(&square));
+        let arrow = [Vec2::ZERO, Vec2::new(10.0, 5.0), Vec2::new(0.0, 10.0), Vec2::new(3.0, 5.0)];
+        assert!(!crosses_itself(&arrow));
+    }
+
+    #[test]
+    fn static_object_with_properties_is_valid() {
+        let level = VALID_LEVEL.replace(
+            r#"<object id="1" x="0" y="200">"#,
+            r#"<object id="1" x="0" y="200">
+   <properties>
+    <property name="restitution" type="float" value="0.5"/>
+   </properties>"#,
+        );
+        assert_eq!(validate(&level), Vec::new());
+    }
+
+    #[test]
+    fn pop_all_level_without_balls() {
+        let level = VALID_LEVEL.replace(r#"<object id="3" x="200" y="50"/>"#, "");
+        let problems = validate(&level);
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].message.contains("pop all"));
+    }
+
+    #[test]
+    fn pop_count_level_without_balls_is_not_flagged_for_its_goal() {
+        let level = VALID_LEVEL
+            .replace(r#"<object id="3" x="200" y="50"/>"#, "")
+            .replace(
+                r#"tileheight="32">"#,
+                r#"tileheight="32">
+ <properties>
+  <property name="goal" value="pop_count"/>
+ </properties>"#,
+            );
+        assert_eq!(validate(&level), Vec::new());
+    }
+}
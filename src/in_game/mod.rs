@@ -1,8 +1,18 @@
+mod aim_preview;
+pub(crate) mod bindings;
 mod camera;
+mod hud;
 mod input;
-mod player;
-mod balls;
-mod levels;
+pub(crate) mod player;
+pub(crate) mod balls;
+pub(crate) mod campaign;
+pub(crate) mod levels;
+pub(crate) mod outcome;
+mod physics_layers;
+pub(crate) mod replay;
+pub(crate) mod scoring;
+pub(crate) mod simulation;
+pub(crate) mod states;
 
 use crate::in_game::camera::camera_plugin;
 use crate::in_game::input::input_plugin;
@@ -10,24 +20,61 @@ use avian2d::prelude::RigidBody;
 use bevy::prelude::*;
 use balls::{ammo_ball, level_ball};
 use crate::in_game::balls::balls_plugin;
-use crate::in_game::levels::{CurrentLevel, LevelLoadingPlugin};
+use crate::in_game::levels::LevelLoadingPlugin;
 
 pub(super) fn in_game_plugin(app: &mut App) {
+    app.add_plugins((gameplay_plugin, presentation_plugin));
+}
+
+/// Levels, shooting, splitting, scoring and the campaign. Needs no window, input or audio,
+/// so it also runs headless.
+pub(crate) fn gameplay_plugin(app: &mut App) {
     app.add_plugins((
-        camera_plugin,
-        input_plugin,
+        states::states_plugin,
+        simulation::simulation_plugin,
         player::player_plugin,
         balls_plugin,
         LevelLoadingPlugin,
+        outcome::outcome_plugin,
+        campaign::campaign_plugin,
+        scoring::scoring_plugin,
+        replay::replay_plugin,
+    ));
+}
+
+/// Everything the player sees, hears and touches
+fn presentation_plugin(app: &mut App) {
+    app.add_plugins((
+        camera_plugin,
+        input_plugin,
+        player::player_presentation_plugin,
+        aim_preview::aim_preview_plugin,
+        balls::balls_presentation_plugin,
+        hud::hud_plugin,
     ));
-    app.add_systems(Startup, start_level);
 }
 
-fn start_level(
-    mut commands: Commands,
-) {
-    // Load the first level
-    commands.insert_resource(CurrentLevel {
-        path: "assets/levels/level_1.tmx".to_string(),
-    });
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::in_game::levels::CurrentLevel;
+    use crate::settings::settings_plugin;
+    use avian2d::PhysicsPlugins;
+    use bevy::state::app::StatesPlugin;
+
+    #[test]
+    fn gameplay_runs_headless_before_a_level_started() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), TransformPlugin, StatesPlugin))
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .init_asset::<Mesh>()
+            .add_plugins(PhysicsPlugins::default())
+            .add_plugins((settings_plugin, gameplay_plugin));
+
+        for _ in 0..5 {
+            app.update();
+        }
+        assert!(!app.world().contains_resource::<CurrentLevel>());
+    }
 }
@@ -0,0 +1,336 @@
+use crate::in_game::balls::ammo_ball::{AmmoBall, ShotFired, SplitChain};
+use crate::in_game::balls::level_ball::{BallPopped, LevelBall};
+use crate::in_game::levels::properties::TiledProperties;
+use crate::in_game::player::Ammo;
+use crate::in_game::scoring::ScoringSystems;
+use crate::in_game::simulation::GameplaySystems;
+use crate::in_game::states::GameState;
+use avian2d::prelude::LinearVelocity;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+pub(super) fn outcome_plugin(app: &mut App) {
+    app.add_event::<ShotSettled>()
+        .add_event::<OutOfAmmo>()
+        .add_event::<LevelCompleted>()
+        .add_event::<LevelFailed>()
+        // Runs on the fixed clock next to the physics, so shots settle the same way every time
+        .add_systems(
+            FixedUpdate,
+            (
+                // Shots keep settling after the level is over, so their scores get summed up
+                (track_shots, settle_shots).chain(),
+                (detect_out_of_ammo, evaluate_outcome, log_outcome, finish_level)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+                .chain()
+                .in_set(GameplaySystems::Track)
+                .before(ScoringSystems)
+                .run_if(resource_exists::<LevelProgress>),
+        );
+}
+
+/// What the player has to do to complete the level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelGoal {
+    /// Pop every ball in the level
+    PopAll,
+    /// Pop this many balls in total
+    PopCount(u32),
+    /// Pop this many balls with a single shot
+    OneShot(u32),
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct LevelGoals {
+    pub goal: LevelGoal,
+}
+
+impl LevelGoals {
+    pub fn from_properties(properties: &TiledProperties) -> Self {
+        let goal_count = properties.get("goal_count").unwrap_or(1);
+        let goal = match properties.get_str("goal").unwrap_or("pop_all") {
+            "pop_all" => LevelGoal::PopAll,
+            "pop_count" => LevelGoal::PopCount(goal_count),
+            "one_shot" => LevelGoal::OneShot(goal_count),
+            other => {
+                warn!("Unknown level goal {}, using pop_all", other);
+                LevelGoal::PopAll
+            }
+        };
+
+        Self { goal }
+    }
+}
+
+/// Progress through the running level, reset whenever a level is spawned
+#[derive(Resource, Debug, Default)]
+pub struct LevelProgress {
+    pub shots_fired: u32,
+    pub total_pops: u32,
+    /// Most balls popped by a single shot
+    pub best_shot_pops: u32,
+    pub shots_settled: u32,
+    pub out_of_ammo: bool,
+    pub finished: bool,
+    /// The level finished with its goal reached, it failed if it finished without
+    pub goal_reached: bool,
+    active_shots: HashMap<u32, ActiveShot>,
+}
+
+impl LevelProgress {
+    /// True when no shot is still bouncing around
+    pub fn all_shots_settled(&self) -> bool {
+        self.active_shots.is_empty()
+    }
+
+    /// The goal was reached. Known as soon as the step that reached it, unlike the [`GameState`] that follows.
+    pub fn cleared(&self) -> bool {
+        self.finished && self.goal_reached
+    }
+
+    /// The level ran out of ammo before reaching its goal
+    pub fn failed(&self) -> bool {
+        self.finished && !self.goal_reached
+    }
+}
+
+#[derive(Debug)]
+struct ActiveShot {
+    pops: u32,
+    last_activity: f32,
+}
+
+/// Sent when a shot and its chain reaction have come to rest
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ShotSettled {
+    pub ammo_id: u32,
+    pub pops: u32,
+}
+
+/// Sent once the last shot of the player's ammo has settled
+#[derive(Event, Clone, Copy, Debug)]
+pub struct OutOfAmmo;
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct LevelCompleted {
+    pub shots_used: u32,
+    pub total_pops: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelFailReason {
+    OutOfShots,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct LevelFailed {
+    pub reason: LevelFailReason,
+}
+
+// A shot settles once nothing in its chain popped for this long and its ammo ball came to rest
+const SETTLE_DELAY: f32 = 1.5;
+// Balls with full restitution can bounce forever, so give up waiting eventually
+const SETTLE_TIMEOUT: f32 = 8.0;
+const RESTING_SPEED: f32 = 20.0;
+
+fn track_shots(
+    mut shots_fired: EventReader<ShotFired>,
+    mut balls_popped: EventReader<BallPopped>,
+    mut progress: ResMut<LevelProgress>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+
+    for shot in shots_fired.read() {
+        progress.shots_fired += 1;
+        progress.active_shots.insert(
+            shot.ammo_id,
+            ActiveShot {
+                pops: 0,
+                last_activity: now,
+            },
+        );
+    }
+
+    for popped in balls_popped.read() {
+        progress.total_pops += 1;
+
+        let Some(shot) = popped.ammo_id.and_then(|id| progress.active_shots.get_mut(&id)) else {
+            continue;
+        };
+        shot.pops += 1;
+        shot.last_activity = now;
+        let pops = shot.pops;
+        progress.best_shot_pops = progress.best_shot_pops.max(pops);
+    }
+}
+
+fn settle_shots(
+    mut progress: ResMut<LevelProgress>,
+    mut settled_events: EventWriter<ShotSettled>,
+    ammo_balls: Query<(&SplitChain, &LinearVelocity), With<AmmoBall>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+
+    let mut settled = Vec::new();
+    for (ammo_id, shot) in progress.active_shots.iter() {
+        let idle_time = now - shot.last_activity;
+        let ammo_moving = ammo_balls
+            .iter()
+            .any(|(chain, velocity)| chain.ammo_id == *ammo_id && velocity.length() > RESTING_SPEED);
+
+        if (idle_time >= SETTLE_DELAY && !ammo_moving) || idle_time >= SETTLE_TIMEOUT {
+            settled.push(ShotSettled {
+                ammo_id: *ammo_id,
+                pops: shot.pops,
+            });
+        }
+    }
+
+    for shot in settled {
+        progress.active_shots.remove(&shot.ammo_id);
+        progress.shots_settled += 1;
+        settled_events.write(shot);
+    }
+}
+
+fn detect_out_of_ammo(
+    mut progress: ResMut<LevelProgress>,
+    ammo: Query<&Ammo>,
+    mut out_of_ammo_events: EventWriter<OutOfAmmo>,
+) {
+    // Players without an ammo limit never run out
+    if progress.out_of_ammo || ammo.is_empty() {
+        return;
+    }
+
+    if ammo.iter().all(|ammo| ammo.remaining == 0) && progress.all_shots_settled() {
+        progress.out_of_ammo = true;
+        out_of_ammo_events.write(OutOfAmmo);
+    }
+}
+
+fn evaluate_outcome(
+    goals: Res<LevelGoals>,
+    mut progress: ResMut<LevelProgress>,
+    level_balls: Query<(), With<LevelBall>>,
+    mut out_of_ammo_events: EventReader<OutOfAmmo>,
+    mut completed_events: EventWriter<LevelCompleted>,
+    mut failed_events: EventWriter<LevelFailed>,
+) {
+    if progress.finished {
+        return;
+    }
+
+    let goal_reached = match goals.goal {
+        // A level without balls isn't cleared before the player even shot
+        LevelGoal::PopAll => level_balls.is_empty() && progress.shots_fired > 0,
+        LevelGoal::PopCount(count) => progress.total_pops >= count,
+        LevelGoal::OneShot(count) => progress.best_shot_pops >= count,
+    };
+
+    if goal_reached {
+        progress.finished = true;
+        progress.goal_reached = true;
+        completed_events.write(LevelCompleted {
+            shots_used: progress.shots_fired,
+            total_pops: progress.total_pops,
+        });
+        return;
+    }
+
+    // Only fail once the last shot has played out completely, it might still reach the goal
+    if out_of_ammo_events.read().last().is_some() {
+        progress.finished = true;
+        failed_events.write(LevelFailed {
+            reason: LevelFailReason::OutOfShots,
+        });
+    }
+}
+
+fn log_outcome(mut completed_events: EventReader<LevelCompleted>, mut failed_events: EventReader<LevelFailed>) {
+    for completed in completed_events.read() {
+        info!("Level completed: {:?}", completed);
+    }
+    for failed in failed_events.read() {
+        info!("Level failed: {:?}", failed);
+    }
+}
+
+fn finish_level(
+    mut completed_events: EventReader<LevelCompleted>,
+    mut failed_events: EventReader<LevelFailed>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if completed_events.read().last().is_some() {
+        next_state.set(GameState::LevelComplete);
+    } else if failed_events.read().last().is_some() {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_level_is_neither_cleared_nor_failed() {
+        let progress = LevelProgress::default();
+        assert!(!progress.cleared());
+        assert!(!progress.failed());
+    }
+
+    #[test]
+    fn running_out_of_ammo_fails() {
+        let progress = LevelProgress {
+            out_of_ammo: true,
+            finished: true,
+            ..default()
+        };
+        assert!(progress.failed());
+        assert!(!progress.cleared());
+    }
+
+    #[test]
+    fn reaching_the_goal_with_the_last_shot_clears() {
+        let progress = LevelProgress {
+            out_of_ammo: true,
+            finished: true,
+            goal_reached: true,
+            ..default()
+        };
+        assert!(progress.cleared());
+        assert!(!progress.failed());
+    }
+
+    fn pop_all_app(shots_fired: u32) -> App {
+        let mut app = App::new();
+        app.add_event::<OutOfAmmo>()
+            .add_event::<LevelCompleted>()
+            .add_event::<LevelFailed>()
+            .insert_resource(LevelGoals { goal: LevelGoal::PopAll })
+            .insert_resource(LevelProgress {
+                shots_fired,
+                ..default()
+            })
+            .add_systems(Update, evaluate_outcome);
+        app
+    }
+
+    #[test]
+    fn empty_level_is_not_cleared_before_a_shot() {
+        let mut app = pop_all_app(0);
+        app.update();
+        assert!(!app.world().resource::<LevelProgress>().finished);
+    }
+
+    #[test]
+    fn no_balls_left_after_a_shot_clears() {
+        let mut app = pop_all_app(1);
+        app.update();
+        assert!(app.world().resource::<LevelProgress>().cleared());
+    }
+}
@@ -0,0 +1,10 @@
+use avian2d::prelude::*;
+
+/// Collision layers used to let level geometry block only some kinds of balls
+#[derive(PhysicsLayer, Clone, Copy, Debug, Default)]
+pub enum GameLayer {
+    #[default]
+    Default,
+    Ball,
+    Ammo,
+}
@@ -1,16 +1,25 @@
 use crate::in_game::balls::ammo_ball::AmmoBall;
-use crate::in_game::input::{PlayerInputContext, Shoot, IncreaseForce, DecreaseForce};
+use crate::in_game::input::{PlayerInputContext, Shoot, ChargeShot, IncreaseForce, DecreaseForce, Aim, AdjustForce};
 use avian2d::prelude::ExternalImpulse;
 use bevy::prelude::*;
-use bevy_enhanced_input::events::{Started, Fired};
+use bevy_enhanced_input::events::{Started, Ongoing, Fired};
 use bevy_enhanced_input::prelude::Actions;
 use std::f32::consts::PI;
 use crate::in_game::balls::initial_velocity::InitialVelocity;
-use bevy::window::PrimaryWindow;
+use crate::in_game::replay::ReplayPlayback;
+use crate::in_game::simulation::GameplaySystems;
+use crate::in_game::states::GameState;
+use crate::settings::{Settings, ShootMode};
+use bevy::window::{CursorMoved, PrimaryWindow};
 
 #[derive(Component)]
+#[require(ShootingForce)]
 pub struct Player;
 
+/// Which of the level's player spawns this player came from
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerIndex(pub u32);
+
 #[derive(Component)]
 struct GunGizmo;
 
@@ -22,7 +31,100 @@ pub struct ShootingForce {
     value: f32,
     min: f32,
     max: f32,
+    /// Change per press of a force key
     step: f32,
+    /// Change per second while a trigger is fully pressed, or the force keys are held with ramping on
+    rate: f32,
+}
+
+/// Shots the player has left in this level. Players without it can shoot as much as they like.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Ammo {
+    pub remaining: u32,
+    pub max: u32,
+}
+
+impl Ammo {
+    pub fn new(max: u32) -> Self {
+        Self { remaining: max, max }
+    }
+}
+
+/// Shoot mode the level picked for the player, over the one in the settings
+#[derive(Component, Debug, Clone, Copy)]
+pub struct LevelShootMode(pub ShootMode);
+
+impl LevelShootMode {
+    pub fn resolve(level_shoot_mode: Option<&LevelShootMode>, settings: &Settings) -> ShootMode {
+        level_shoot_mode.map_or(settings.shoot_mode, |mode| mode.0)
+    }
+}
+
+/// A shot being charged, for as long as shoot is held in the charging shoot modes
+#[derive(Component, Debug)]
+struct Charging {
+    mode: ShootMode,
+    elapsed: f32,
+}
+
+/// The device the player aims with, whichever was used last
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AimDevice {
+    #[default]
+    Mouse,
+    Gamepad,
+}
+
+/// A shot the player asked for. It's fired on the next fixed step, so it lines up with the physics.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ShotRequested {
+    pub player: Entity,
+    /// Rotation of the player around the z axis when shooting
+    pub angle: f32,
+    pub force: f32,
+}
+
+impl ShotRequested {
+    fn aimed(player: Entity, transform: &Transform, force: &ShootingForce) -> Self {
+        Self {
+            player,
+            angle: transform.rotation.to_euler(EulerRot::XYZ).2,
+            force: force.value,
+        }
+    }
+}
+
+/// Sent when the player tries to shoot without any ammo left
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ShotRefused {
+    pub player: Entity,
+}
+
+impl ShootingForce {
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    pub fn min(&self) -> f32 {
+        self.min
+    }
+
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    /// Where the force is between its minimum and maximum, from 0.0 to 1.0
+    pub fn fraction(&self) -> f32 {
+        (self.value - self.min) / (self.max - self.min)
+    }
+
+    pub fn set_value(&mut self, value: f32) {
+        self.value = value.clamp(self.min, self.max);
+    }
+
+    fn set_fraction(&mut self, fraction: f32) {
+        self.value = self.min + fraction.clamp(0.0, 1.0) * (self.max - self.min);
+    }
 }
 
 impl Default for ShootingForce {
@@ -32,22 +134,62 @@ impl Default for ShootingForce {
             min: 5_000.0,
             max: 100_000.0,
             step: 1_000.0,
+            rate: 60_000.0,
         }
     }
 }
 
 pub(super) fn player_plugin(app: &mut App) {
-    app.add_observer(observe_add_player)
+    app.add_event::<ShotRefused>()
+        .add_event::<ShotRequested>()
+        .add_systems(
+            FixedUpdate,
+            fire_requested_shots
+                .in_set(GameplaySystems::Shoot)
+                .run_if(in_state(GameState::Playing)),
+        );
+}
+
+/// Input, aiming and the looks of the players
+pub(super) fn player_presentation_plugin(app: &mut App) {
+    app.init_resource::<AimDevice>()
+        .add_observer(observe_add_player)
+        .add_observer(react_to_aim)
+        .add_observer(react_to_adjust_force)
         .add_observer(react_to_shoot)
+        .add_observer(start_charging)
+        .add_observer(charge_shot)
+        .add_observer(release_charged_shot)
         .add_observer(react_to_increase_force)
         .add_observer(react_to_decrease_force)
-        .add_systems(Update, (rotate_player_to_mouse, update_force_gizmo).chain());
+        .add_observer(ramp_increase_force)
+        .add_observer(ramp_decrease_force)
+        // Replays move the players themselves
+        .add_systems(
+            OnEnter(GameState::Playing),
+            enable_player_input.run_if(not(resource_exists::<ReplayPlayback>)),
+        )
+        .add_systems(OnExit(GameState::Playing), disable_player_input)
+        .add_systems(
+            Update,
+            (
+                detect_aim_device,
+                rotate_player_to_mouse.run_if(resource_equals(AimDevice::Mouse)),
+                update_force_gizmo,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
 }
 
 const GUN_LENGTH: f32 = 100.0;
 const FORCE_GIZMO_WIDTH: f32 = 80.0; // This is now the length of the force indicator
 const FORCE_GIZMO_THICKNESS: f32 = 4.0;
 const FORCE_OFFSET: f32 = 10.0; // Distance from gun barrel
+// Seconds a charging shot takes to go from the minimum force to the maximum
+const CHARGE_TIME: f32 = 1.2;
+// Stick tilt that counts as using the gamepad, so a drifting stick doesn't take over from the mouse
+const GAMEPAD_ACTIVITY_THRESHOLD: f32 = 0.3;
 
 fn observe_add_player(
     trigger: Trigger<OnAdd, Player>,
@@ -75,8 +217,6 @@ fn observe_add_player(
             custom_size: Some(Vec2::splat(100.0)),
             ..Default::default()
         },
-        Actions::<PlayerInputContext>::default(),
-        ShootingForce::default(),
         children![
             // Gun gizmo
             (
@@ -106,25 +246,172 @@ fn observe_add_player(
     ));
 }
 
+// Players only get their input actions while the game is being played, so nothing fires while paused
+fn enable_player_input(mut commands: Commands, players: Query<Entity, With<Player>>) {
+    for player in players.iter() {
+        commands.entity(player).insert(Actions::<PlayerInputContext>::default());
+    }
+}
+
+fn disable_player_input(mut commands: Commands, players: Query<Entity, With<Player>>) {
+    for player in players.iter() {
+        commands.entity(player).remove::<Actions<PlayerInputContext>>();
+    }
+}
+
 fn react_to_shoot(
     trigger: Trigger<Started<Shoot>>,
+    players: Query<(&Transform, &ShootingForce)>,
+    mut shot_requests: EventWriter<ShotRequested>,
+) {
+    if let Ok((transform, force)) = players.get(trigger.target()) {
+        shot_requests.write(ShotRequested::aimed(trigger.target(), transform, force));
+    }
+}
+
+// Charging starts from the minimum force every time
+fn start_charging(
+    trigger: Trigger<Started<ChargeShot>>,
+    mut commands: Commands,
+    mut players: Query<(&mut ShootingForce, Option<&LevelShootMode>)>,
+    settings: Res<Settings>,
+) {
+    let Ok((mut force, level_shoot_mode)) = players.get_mut(trigger.target()) else {
+        return;
+    };
+    force.set_fraction(0.0);
+    commands.entity(trigger.target()).insert(Charging {
+        mode: LevelShootMode::resolve(level_shoot_mode, &settings),
+        elapsed: 0.0,
+    });
+}
+
+fn charge_shot(
+    trigger: Trigger<Ongoing<ChargeShot>>,
+    time: Res<Time>,
+    mut players: Query<(&mut ShootingForce, &mut Charging)>,
+) {
+    let Ok((mut force, mut charging)) = players.get_mut(trigger.target()) else {
+        return;
+    };
+    charging.elapsed += time.delta_secs();
+
+    let progress = charging.elapsed / CHARGE_TIME;
+    let fraction = match charging.mode {
+        // Swinging goes up and back down again, over and over
+        ShootMode::Swing => {
+            let phase = progress % 2.0;
+            if phase <= 1.0 { phase } else { 2.0 - phase }
+        }
+        _ => progress,
+    };
+    force.set_fraction(fraction);
+}
+
+fn release_charged_shot(
+    trigger: Trigger<Fired<ChargeShot>>,
     mut commands: Commands,
-    transforms: Query<&Transform>,
-    forces: Query<&ShootingForce>,
+    players: Query<(&Transform, &ShootingForce)>,
+    mut shot_requests: EventWriter<ShotRequested>,
 ) {
-    let transform = transforms.get(trigger.target()).unwrap();
-    let position = transform.translation;
+    commands.entity(trigger.target()).remove::<Charging>();
+    if let Ok((transform, force)) = players.get(trigger.target()) {
+        shot_requests.write(ShotRequested::aimed(trigger.target(), transform, force));
+    }
+}
+
+pub(super) fn fire_requested_shots(
+    mut commands: Commands,
+    mut shot_requests: EventReader<ShotRequested>,
+    mut players: Query<(&mut Transform, Option<&mut Ammo>), With<Player>>,
+) {
+    for request in shot_requests.read() {
+        let Ok((mut transform, ammo)) = players.get_mut(request.player) else {
+            continue;
+        };
+
+        if let Some(mut ammo) = ammo {
+            if ammo.remaining == 0 {
+                info!("Out of ammo");
+                commands.send_event(ShotRefused {
+                    player: request.player,
+                });
+                continue;
+            }
+            ammo.remaining -= 1;
+        }
+
+        // The gun points where the shot went, even if the player moved since asking for it
+        transform.rotation = Quat::from_rotation_z(request.angle);
+        let (position, direction) = muzzle(&transform);
+
+        commands.spawn((
+            AmmoBall,
+            InitialVelocity(direction * request.force),
+            Transform::from_translation(position.extend(transform.translation.z)),
+        ));
+    }
+}
+
+/// Where ammo leaves the gun of a player, and the direction it flies in
+pub fn muzzle(transform: &Transform) -> (Vec2, Vec2) {
     let rotation = transform.rotation.to_euler(EulerRot::XYZ).2 - PI / 2.0;
-    let force = forces.get(trigger.target()).unwrap();
-    let initial_velocity = Vec2::from_angle(rotation) * force.value;
-
-    commands.spawn((
-        AmmoBall,
-        InitialVelocity(initial_velocity),
-        Transform::from_translation(
-            position + (Vec2::from_angle(rotation) * GUN_LENGTH).extend(0.0),
-        ),
-    ));
+    let direction = Vec2::from_angle(rotation);
+    (transform.translation.truncate() + direction * GUN_LENGTH, direction)
+}
+
+/// Rotation that points the gun of a player in the direction
+fn aim_rotation(direction: Vec2) -> Quat {
+    Quat::from_rotation_z(gun_angle(direction.y.atan2(direction.x)))
+}
+
+/// Rotation of the player around the z axis that shoots at this angle, counterclockwise from the x axis.
+/// Shots and replays store this rotation, the gun points down at zero.
+pub fn gun_angle(shot_angle: f32) -> f32 {
+    shot_angle + PI / 2.0
+}
+
+fn detect_aim_device(
+    mut aim_device: ResMut<AimDevice>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+) {
+    let gamepad_used = gamepads.iter().any(|gamepad| {
+        gamepad.right_stick().length() > GAMEPAD_ACTIVITY_THRESHOLD
+            || gamepad.left_stick().length() > GAMEPAD_ACTIVITY_THRESHOLD
+            || gamepad.get_just_pressed().next().is_some()
+    });
+    let mouse_used = cursor_moved.read().count() > 0 || mouse_buttons.get_just_pressed().len() > 0;
+
+    let device = if gamepad_used {
+        AimDevice::Gamepad
+    } else if mouse_used {
+        AimDevice::Mouse
+    } else {
+        return;
+    };
+    if aim_device.set_if_neq(device) {
+        info!("Aiming with {:?}", device);
+    }
+}
+
+// The stick wins over the mouse, as the mouse aim stops as soon as the gamepad is used
+fn react_to_aim(trigger: Trigger<Fired<Aim>>, mut transforms: Query<&mut Transform, With<Player>>) {
+    if let Ok(mut transform) = transforms.get_mut(trigger.target()) {
+        transform.rotation = aim_rotation(trigger.value);
+    }
+}
+
+fn react_to_adjust_force(
+    trigger: Trigger<Fired<AdjustForce>>,
+    time: Res<Time>,
+    mut forces: Query<&mut ShootingForce>,
+) {
+    if let Ok(mut force) = forces.get_mut(trigger.target()) {
+        let change = trigger.value * force.rate * time.delta_secs();
+        force.value = (force.value + change).clamp(force.min, force.max);
+    }
 }
 
 fn rotate_player_to_mouse(
@@ -155,30 +442,66 @@ fn rotate_player_to_mouse(
 
     for mut transform in player_query.iter_mut() {
         let player_pos = transform.translation.truncate();
-        let direction = world_position - player_pos;
-        let angle = direction.y.atan2(direction.x) + PI / 2.0;
-        transform.rotation = Quat::from_rotation_z(angle);
+        transform.rotation = aim_rotation(world_position - player_pos);
     }
 }
 
+// One step per press, unless the keys ramp the force while held
 fn react_to_increase_force(
-    trigger: Trigger<Fired<IncreaseForce>>,
+    trigger: Trigger<Started<IncreaseForce>>,
+    settings: Res<Settings>,
     mut forces: Query<&mut ShootingForce>,
 ) {
+    if settings.ramp_force_keys {
+        return;
+    }
     if let Ok(mut force) = forces.get_mut(trigger.target()) {
         force.value = (force.value + force.step).min(force.max);
     }
 }
 
 fn react_to_decrease_force(
-    trigger: Trigger<Fired<DecreaseForce>>,
+    trigger: Trigger<Started<DecreaseForce>>,
+    settings: Res<Settings>,
     mut forces: Query<&mut ShootingForce>,
 ) {
+    if settings.ramp_force_keys {
+        return;
+    }
     if let Ok(mut force) = forces.get_mut(trigger.target()) {
         force.value = (force.value - force.step).max(force.min);
     }
 }
 
+// Fired every frame while held, so the change follows the frame time
+fn ramp_increase_force(
+    trigger: Trigger<Fired<IncreaseForce>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut forces: Query<&mut ShootingForce>,
+) {
+    if !settings.ramp_force_keys {
+        return;
+    }
+    if let Ok(mut force) = forces.get_mut(trigger.target()) {
+        force.value = (force.value + force.rate * time.delta_secs()).min(force.max);
+    }
+}
+
+fn ramp_decrease_force(
+    trigger: Trigger<Fired<DecreaseForce>>,
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut forces: Query<&mut ShootingForce>,
+) {
+    if !settings.ramp_force_keys {
+        return;
+    }
+    if let Ok(mut force) = forces.get_mut(trigger.target()) {
+        force.value = (force.value - force.rate * time.delta_secs()).max(force.min);
+    }
+}
+
 fn update_force_gizmo(
     force_query: Query<&ShootingForce>,
     mut gizmos: Query<(&mut Transform, &Gizmo), With<ForceGizmo>>,
@@ -187,7 +510,7 @@ fn update_force_gizmo(
     for force in force_query.iter() {
         for (mut transform, gizmo) in gizmos.iter_mut() {
             // Calculate force percentage and scale
-            let force_percent = (force.value - force.min) / (force.max - force.min);
+            let force_percent = force.fraction();
             // Ensure minimum scale of 0.2 (20%) for visibility
             let scale = 0.2 + (force_percent * 0.8);
             
@@ -0,0 +1,354 @@
+//! Replays of level attempts. A replay holds the level, its seed and every shot with the fixed step it
+//! was fired on, which is enough to play the attempt out again exactly, see [`crate::in_game::simulation`].
+//!
+//! Every attempt is recorded and written to `replays/<level id>.last.ron` in the save directory when it
+//! ends. Start the game with `--replay <file>` to watch one.
+
+use crate::in_game::campaign::{Campaign, CampaignHandle, StartLevel};
+use crate::in_game::levels::CurrentLevel;
+use crate::in_game::player::{Player, PlayerIndex, ShootingForce, ShotRequested, fire_requested_shots};
+use crate::in_game::simulation::{self, GameplaySystems, LevelSeed, LevelTick};
+use crate::in_game::states::{AppState, GameState, InLevel};
+use crate::save::save_dir;
+use bevy::asset::ron;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub(super) fn replay_plugin(app: &mut App) {
+    app.add_systems(
+        OnEnter(InLevel),
+        (
+            start_recording.run_if(not(resource_exists::<ReplayPlayback>)),
+            rewind_playback
+                .run_if(resource_exists::<ReplayPlayback>)
+                .before(simulation::reseed_rngs),
+        ),
+    )
+    .add_systems(OnExit(InLevel), write_recording)
+    .add_systems(
+        FixedUpdate,
+        (
+            play_back_shots.run_if(resource_exists::<ReplayPlayback>),
+            record_shots.run_if(resource_exists::<ReplayRecording>),
+        )
+            .chain()
+            .in_set(GameplaySystems::Shoot)
+            .before(fire_requested_shots)
+            .run_if(in_state(GameState::Playing)),
+    )
+    .add_systems(
+        Update,
+        start_playback.run_if(resource_exists::<ReplayPlayback>.and(in_state(AppState::MainMenu))),
+    );
+}
+
+pub const REPLAY_VERSION: u32 = 1;
+const REPLAY_DIR_NAME: &str = "replays";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub level_id: String,
+    pub seed: u64,
+    pub shots: Vec<ReplayShot>,
+}
+
+/// A shot and where it was fired from
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ReplayShot {
+    /// Fixed step of the level the shot was fired on
+    pub tick: u64,
+    /// [`PlayerIndex`] of the player that fired it
+    pub player: u32,
+    /// Where the player stood, shots without one are fired from wherever the player is
+    #[serde(default)]
+    pub position: Option<Vec2>,
+    pub angle: f32,
+    pub force: f32,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    /// Recorded by a newer build of the game
+    NewerVersion(u32),
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Replay file IO error: {}", e),
+            Self::Parse(e) => write!(f, "Failed to parse replay file: {}", e),
+            Self::Serialize(e) => write!(f, "Failed to serialize replay: {}", e),
+            Self::NewerVersion(version) => write!(
+                f,
+                "Replay version {} is newer than the supported version {}",
+                version, REPLAY_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl Replay {
+    pub fn read(path: &Path) -> Result<Self, ReplayError> {
+        let text = fs::read_to_string(path).map_err(ReplayError::Io)?;
+        let replay: Replay = ron::from_str(&text).map_err(ReplayError::Parse)?;
+        if replay.version > REPLAY_VERSION {
+            return Err(ReplayError::NewerVersion(replay.version));
+        }
+        Ok(replay)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), ReplayError> {
+        // One shot per line keeps replays short and still readable in bug reports
+        let config = ron::ser::PrettyConfig::default().depth_limit(2);
+        let text = ron::ser::to_string_pretty(self, config).map_err(ReplayError::Serialize)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(ReplayError::Io)?;
+        }
+        fs::write(path, text).map_err(ReplayError::Io)
+    }
+}
+
+/// Where the last attempt of a level gets written
+pub fn last_replay_path(level_id: &str) -> Option<PathBuf> {
+    Some(save_dir()?.join(REPLAY_DIR_NAME).join(format!("{}.last.ron", level_id)))
+}
+
+/// The attempt being recorded
+#[derive(Resource, Debug)]
+pub struct ReplayRecording(pub Replay);
+
+/// A replay being watched. Players take no input while it exists, their shots come from the replay.
+#[derive(Resource, Debug)]
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    /// Shots of the replay fired so far
+    next_shot: usize,
+    started: bool,
+    /// Plays on the seed of the level instead of the one in the replay, for shots that weren't recorded
+    use_level_seed: bool,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next_shot: 0,
+            started: false,
+            use_level_seed: false,
+        }
+    }
+
+    /// Plays shots that weren't recorded on the level, its seed is filled in once the level started
+    pub fn with_level_seed(replay: Replay) -> Self {
+        Self {
+            use_level_seed: true,
+            ..Self::new(replay)
+        }
+    }
+
+    /// True once every shot of the replay was asked for
+    pub fn finished(&self) -> bool {
+        self.next_shot >= self.replay.shots.len()
+    }
+
+    /// Fixed step of the last shot of the replay
+    pub fn last_shot_tick(&self) -> u64 {
+        self.replay.shots.last().map_or(0, |shot| shot.tick)
+    }
+}
+
+// Waits for the campaign to know where the level of the replay is
+fn start_playback(
+    mut commands: Commands,
+    mut playback: ResMut<ReplayPlayback>,
+    campaign_handle: Option<Res<CampaignHandle>>,
+    campaigns: Res<Assets<Campaign>>,
+    mut start_level: EventWriter<StartLevel>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    if playback.started {
+        return;
+    }
+    let Some(campaign) = campaign_handle.as_ref().and_then(|handle| campaigns.get(&handle.0)) else {
+        return;
+    };
+    playback.started = true;
+
+    let level_id = &playback.replay.level_id;
+    if campaign.level(level_id).is_none() {
+        error!("The replay is of an unknown level {}", level_id);
+        // Back to playing normally
+        commands.remove_resource::<ReplayPlayback>();
+        return;
+    }
+    info!("Playing back a replay of {}", level_id);
+    start_level.write(StartLevel { id: level_id.clone() });
+    app_state.set(AppState::InGame);
+}
+
+// Restarting the level starts the replay over too
+fn rewind_playback(mut playback: ResMut<ReplayPlayback>, mut seed: ResMut<LevelSeed>) {
+    playback.next_shot = 0;
+    if playback.use_level_seed {
+        playback.replay.seed = seed.0;
+    } else if seed.0 != playback.replay.seed {
+        warn!("The level seed changed since the replay was recorded, using the seed of the replay");
+        seed.0 = playback.replay.seed;
+    }
+}
+
+fn play_back_shots(
+    mut playback: ResMut<ReplayPlayback>,
+    tick: Res<LevelTick>,
+    mut players: Query<(Entity, &PlayerIndex, &mut Transform, &mut ShootingForce), With<Player>>,
+    mut shot_requests: EventWriter<ShotRequested>,
+) {
+    while let Some(shot) = playback.replay.shots.get(playback.next_shot).copied() {
+        if shot.tick > tick.0 {
+            break;
+        }
+        playback.next_shot += 1;
+
+        let Some((player, _, mut transform, mut force)) =
+            players.iter_mut().find(|(_, index, _, _)| index.0 == shot.player)
+        else {
+            warn!("The replay shoots with player {}, which the level doesn't have", shot.player);
+            continue;
+        };
+        if let Some(position) = shot.position {
+            transform.translation = position.extend(transform.translation.z);
+        }
+        force.set_value(shot.force);
+        shot_requests.write(ShotRequested {
+            player,
+            angle: shot.angle,
+            force: shot.force,
+        });
+    }
+}
+
+fn start_recording(mut commands: Commands, current_level: Option<Res<CurrentLevel>>, seed: Res<LevelSeed>) {
+    let Some(current_level) = current_level else {
+        return;
+    };
+    commands.insert_resource(ReplayRecording(Replay {
+        version: REPLAY_VERSION,
+        level_id: current_level.id.clone(),
+        seed: seed.0,
+        shots: Vec::new(),
+    }));
+}
+
+fn record_shots(
+    mut recording: ResMut<ReplayRecording>,
+    mut shot_requests: EventReader<ShotRequested>,
+    tick: Res<LevelTick>,
+    players: Query<(&PlayerIndex, &Transform)>,
+) {
+    for request in shot_requests.read() {
+        let Ok((index, transform)) = players.get(request.player) else {
+            continue;
+        };
+        recording.0.shots.push(ReplayShot {
+            tick: tick.0,
+            player: index.0,
+            position: Some(transform.translation.truncate()),
+            angle: request.angle,
+            force: request.force,
+        });
+    }
+}
+
+// Attempts without a single shot aren't worth keeping
+fn write_recording(mut commands: Commands, recording: Option<Res<ReplayRecording>>) {
+    let Some(recording) = recording else {
+        return;
+    };
+    commands.remove_resource::<ReplayRecording>();
+    if recording.0.shots.is_empty() {
+        return;
+    }
+
+    let Some(path) = last_replay_path(&recording.0.level_id) else {
+        return;
+    };
+    match recording.0.write(&path) {
+        Ok(()) => info!("Saved replay to {}", path.display()),
+        Err(e) => error!("Failed to save replay: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("splittin-test-{}", std::process::id()))
+            .join(name)
+    }
+
+    #[test]
+    fn replay_round_trips_through_a_file() {
+        let replay = Replay {
+            version: REPLAY_VERSION,
+            level_id: "level_1".to_string(),
+            seed: 0xdead_beef_cafe,
+            shots: vec![
+                ReplayShot {
+                    tick: 12,
+                    player: 0,
+                    position: Some(Vec2::new(-120.5, 33.25)),
+                    angle: 0.123_456,
+                    force: 40_000.0,
+                },
+                ReplayShot {
+                    tick: 400,
+                    player: 0,
+                    position: None,
+                    angle: -2.5,
+                    force: 12_345.6,
+                },
+            ],
+        };
+        let path = temp_path("round_trip.ron");
+        replay.write(&path).unwrap();
+        let read = Replay::read(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(read.version, replay.version);
+        assert_eq!(read.level_id, replay.level_id);
+        assert_eq!(read.seed, replay.seed);
+        assert_eq!(read.shots.len(), replay.shots.len());
+        for (read, written) in read.shots.iter().zip(&replay.shots) {
+            // Floats have to come back bit for bit, or the replay plays out differently
+            assert_eq!(read.tick, written.tick);
+            assert_eq!(read.player, written.player);
+            assert_eq!(read.position, written.position);
+            assert_eq!(read.angle.to_bits(), written.angle.to_bits());
+            assert_eq!(read.force.to_bits(), written.force.to_bits());
+        }
+    }
+
+    #[test]
+    fn newer_replay_is_rejected() {
+        let path = temp_path("newer.ron");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            format!("(version: {}, level_id: \"level_1\", seed: 0, shots: [])", REPLAY_VERSION + 1),
+        )
+        .unwrap();
+        let result = Replay::read(&path);
+        let _ = fs::remove_file(&path);
+        assert!(matches!(result, Err(ReplayError::NewerVersion(_))));
+    }
+}
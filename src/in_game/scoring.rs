@@ -0,0 +1,141 @@
+use crate::in_game::balls::level_ball::BallPopped;
+use crate::in_game::outcome::ShotSettled;
+use crate::in_game::simulation::GameplaySystems;
+use crate::in_game::states::InLevel;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+pub(super) fn scoring_plugin(app: &mut App) {
+    app.init_resource::<Score>()
+        .add_event::<ShotScored>()
+        .add_systems(OnEnter(InLevel), reset_score)
+        .add_systems(
+            FixedUpdate,
+            (score_pops, summarize_shots)
+                .chain()
+                .in_set(ScoringSystems)
+                .in_set(GameplaySystems::Track)
+                .run_if(in_state(InLevel)),
+        );
+}
+
+/// Systems that update the [`Score`] in the fixed steps, run after these to read an up to date score
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScoringSystems;
+
+// Each pop after the first in a chain raises the multiplier by this much
+const COMBO_STEP: f32 = 0.25;
+const MAX_COMBO_MULTIPLIER: f32 = 5.0;
+// Pops of the same chain closer together than this count as one multi-split
+const MULTI_SPLIT_WINDOW: f32 = 0.2;
+// Extra points for every pop after the first one of a multi-split
+const MULTI_SPLIT_BONUS: u32 = 25;
+
+/// Score of the running level
+#[derive(Resource, Debug, Default)]
+pub struct Score {
+    pub total: u32,
+    /// Points of the best single shot
+    pub best_shot: u32,
+    /// Longest chain of pops caused by a single shot
+    pub best_chain: u32,
+    chains: HashMap<u32, ChainScore>,
+}
+
+impl Score {
+    /// Pops so far in the chain of this shot, if it's still running
+    pub fn chain_length(&self, ammo_id: u32) -> Option<u32> {
+        self.chains.get(&ammo_id).map(|chain| chain.pops)
+    }
+
+    /// Pops of the longest chain that is still running
+    pub fn live_chain(&self) -> u32 {
+        self.chains.values().map(|chain| chain.pops).max().unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ChainScore {
+    pops: u32,
+    points: u32,
+    bonus: u32,
+    multiplier: f32,
+    burst: u32,
+    last_pop: f32,
+}
+
+impl ChainScore {
+    fn combo_multiplier(&self) -> f32 {
+        (1.0 + self.pops.saturating_sub(1) as f32 * COMBO_STEP).min(MAX_COMBO_MULTIPLIER)
+    }
+}
+
+/// Sent when a shot has settled, with everything its chain reaction scored
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ShotScored {
+    pub ammo_id: u32,
+    pub pops: u32,
+    /// All points of the shot, bonuses included
+    pub points: u32,
+    pub multi_split_bonus: u32,
+    /// Highest combo multiplier the chain reached
+    pub multiplier: f32,
+}
+
+fn reset_score(mut score: ResMut<Score>) {
+    *score = Score::default();
+}
+
+fn score_pops(mut popped_events: EventReader<BallPopped>, mut score: ResMut<Score>, time: Res<Time>) {
+    let now = time.elapsed_secs();
+
+    for popped in popped_events.read() {
+        // Balls popped without a shot behind them just give their own points
+        let Some(ammo_id) = popped.ammo_id else {
+            score.total += popped.score;
+            continue;
+        };
+
+        let chain = score.chains.entry(ammo_id).or_default();
+        chain.pops += 1;
+
+        chain.burst = if chain.pops > 1 && now - chain.last_pop <= MULTI_SPLIT_WINDOW {
+            chain.burst + 1
+        } else {
+            1
+        };
+        chain.last_pop = now;
+
+        let multiplier = chain.combo_multiplier();
+        chain.multiplier = chain.multiplier.max(multiplier);
+        let bonus = if chain.burst > 1 { MULTI_SPLIT_BONUS } else { 0 };
+        let points = (popped.score as f32 * multiplier).round() as u32 + bonus;
+        chain.points += points;
+        chain.bonus += bonus;
+        let pops = chain.pops;
+
+        score.total += points;
+        score.best_chain = score.best_chain.max(pops);
+    }
+}
+
+fn summarize_shots(
+    mut settled_events: EventReader<ShotSettled>,
+    mut score: ResMut<Score>,
+    mut scored_events: EventWriter<ShotScored>,
+) {
+    for settled in settled_events.read() {
+        let chain = score.chains.remove(&settled.ammo_id).unwrap_or_default();
+        score.best_shot = score.best_shot.max(chain.points);
+
+        let scored = ShotScored {
+            ammo_id: settled.ammo_id,
+            pops: chain.pops,
+            points: chain.points,
+            multi_split_bonus: chain.bonus,
+            multiplier: chain.multiplier.max(1.0),
+        };
+        info!("Shot scored: {:?}", scored);
+        scored_events.write(scored);
+    }
+}
@@ -0,0 +1,115 @@
+//! Keeps the gameplay reproducible. Physics, splitting, ammo and scoring all run in the fixed
+//! schedules with a fixed timestep, randomness is seeded from the level, and collisions are handled
+//! in a stable order. The same shots from the same level then play out the same way.
+//!
+//! In deterministic mode the clock also advances exactly one fixed step per frame, so a run doesn't
+//! depend on frame timing either. Build with the `deterministic` feature to get identical physics
+//! across machines too.
+
+use crate::in_game::states::InLevel;
+use avian2d::prelude::Gravity;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::time::Duration;
+
+pub const SIMULATION_HZ: f64 = 60.0;
+const GRAVITY: f32 = 380.0;
+
+pub(super) fn simulation_plugin(app: &mut App) {
+    app.insert_resource(Time::<Fixed>::from_duration(timestep()))
+        .insert_resource(Gravity(Vec2::NEG_Y * GRAVITY))
+        .configure_sets(
+            FixedUpdate,
+            (GameplaySystems::Shoot, GameplaySystems::Collide, GameplaySystems::Track).chain(),
+        )
+        .insert_resource(LevelSeed(0))
+        .insert_resource(EffectsRng::from_level_seed(0))
+        .init_resource::<LevelTick>()
+        .add_systems(OnEnter(InLevel), (reseed_rngs, reset_level_tick))
+        .add_systems(FixedLast, advance_level_tick.run_if(in_state(InLevel)));
+}
+
+/// Order of the gameplay within each fixed step. What one set sends, the next one sees in the same step.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameplaySystems {
+    /// Fires the shots asked for since the last step
+    Shoot,
+    /// Splits and pops balls that collided in the last physics step
+    Collide,
+    /// Keeps the score and the progress through the level
+    Track,
+}
+
+/// Steps the simulation exactly once per frame, for replays and automated runs
+pub(crate) fn deterministic_plugin(app: &mut App) {
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep()));
+    info!("Running in deterministic mode");
+}
+
+fn timestep() -> Duration {
+    Duration::from_secs_f64(1.0 / SIMULATION_HZ)
+}
+
+/// Seed of the running level, from its `seed` property or its path. Anything random draws from
+/// generators seeded with it, restarting a level starts them over.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelSeed(pub u64);
+
+impl LevelSeed {
+    /// Seeds levels without a `seed` property from their path. FNV-1a stays the same across builds,
+    /// unlike the std hasher.
+    pub fn from_path(path: &str) -> Self {
+        let hash = path.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        Self(hash)
+    }
+}
+
+/// Fixed steps since the running level started. Shots are timed by it, not by the clock.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LevelTick(pub u64);
+
+/// Randomness of sounds and effects. How many of them play depends on timing, so they have a
+/// generator of their own and never change what gameplay code draws.
+#[derive(Resource)]
+pub struct EffectsRng(pub StdRng);
+
+impl EffectsRng {
+    fn from_level_seed(seed: u64) -> Self {
+        // A fixed offset keeps this stream apart from ones seeded with the level seed as is
+        Self(StdRng::seed_from_u64(seed ^ 0x5eed_eff3c7))
+    }
+}
+
+fn reset_level_tick(mut tick: ResMut<LevelTick>) {
+    tick.0 = 0;
+}
+
+fn advance_level_tick(mut tick: ResMut<LevelTick>) {
+    tick.0 += 1;
+}
+
+pub(super) fn reseed_rngs(seed: Res<LevelSeed>, mut effects_rng: ResMut<EffectsRng>) {
+    *effects_rng = EffectsRng::from_level_seed(seed.0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_from_path_is_fnv1a() {
+        // Reference values of 64 bit FNV-1a
+        assert_eq!(LevelSeed::from_path(""), LevelSeed(0xcbf29ce484222325));
+        assert_eq!(LevelSeed::from_path("a"), LevelSeed(0xaf63dc4c8601ec8c));
+    }
+
+    #[test]
+    fn levels_get_different_seeds() {
+        assert_eq!(LevelSeed::from_path("levels/level_1.tmx"), LevelSeed::from_path("levels/level_1.tmx"));
+        assert_ne!(LevelSeed::from_path("levels/level_1.tmx"), LevelSeed::from_path("levels/level_2.tmx"));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::in_game::input::{Pause, Restart};
+use bevy::prelude::*;
+use bevy_enhanced_input::events::Started;
+
+pub(super) fn states_plugin(app: &mut App) {
+    app.init_state::<AppState>()
+        .add_sub_state::<GameState>()
+        .add_computed_state::<InLevel>()
+        .enable_state_scoped_entities::<AppState>()
+        .enable_state_scoped_entities::<GameState>()
+        .enable_state_scoped_entities::<InLevel>()
+        .add_systems(OnEnter(GameState::Paused), pause_time)
+        .add_systems(OnExit(GameState::Paused), resume_time)
+        .add_observer(toggle_pause)
+        .add_observer(restart_level);
+}
+
+#[derive(States, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum AppState {
+    #[default]
+    MainMenu,
+    InGame,
+}
+
+/// Lifecycle of the running level
+#[derive(SubStates, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[source(AppState = AppState::InGame)]
+pub enum GameState {
+    /// Waiting for the level file, the level gets spawned once it's loaded
+    #[default]
+    Loading,
+    Playing,
+    Paused,
+    LevelComplete,
+    GameOver,
+}
+
+/// Exists while a spawned level is around, whatever happens in it.
+/// Level entities are scoped to it, so they get despawned when the level is left or reloaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InLevel;
+
+impl ComputedStates for InLevel {
+    type SourceStates = GameState;
+
+    fn compute(game_state: GameState) -> Option<Self> {
+        match game_state {
+            GameState::Loading => None,
+            _ => Some(InLevel),
+        }
+    }
+}
+
+// Pausing virtual time stops physics and every timer of the level
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn resume_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn toggle_pause(
+    _trigger: Trigger<Started<Pause>>,
+    game_state: Option<Res<State<GameState>>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    match game_state.map(|state| *state.get()) {
+        Some(GameState::Playing) => next_state.set(GameState::Paused),
+        Some(GameState::Paused) => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+fn restart_level(
+    _trigger: Trigger<Started<Restart>>,
+    in_level: Option<Res<State<InLevel>>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // Going back to loading despawns the level and spawns it again from the loaded file
+    if in_level.is_some() {
+        next_state.set(GameState::Loading);
+    }
+}
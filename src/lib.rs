@@ -0,0 +1,52 @@
+mod in_game;
+mod menus;
+mod save;
+mod settings;
+pub mod lint;
+pub mod sim;
+pub mod solver;
+
+use avian2d::PhysicsPlugins;
+use avian2d::prelude::{PhysicsDebugPlugin, PhysicsInterpolationPlugin};
+use bevy::prelude::*;
+use bevy_enhanced_input::prelude::*;
+use crate::in_game::in_game_plugin;
+use crate::in_game::replay::{Replay, ReplayPlayback};
+use crate::in_game::simulation::deterministic_plugin;
+use crate::menus::menus_plugin;
+use crate::save::save_plugin;
+use crate::settings::settings_plugin;
+
+/// Runs the game in a window
+pub fn run() {
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins)
+        .add_plugins(EnhancedInputPlugin)
+        .add_plugins(PhysicsPlugins::default().set(PhysicsInterpolationPlugin::interpolate_all()))
+        .add_plugins(PhysicsDebugPlugin::default(),)
+        .add_plugins((settings_plugin, in_game_plugin, menus_plugin, save_plugin));
+
+    let args: Vec<String> = std::env::args().collect();
+    let replay_path = args
+        .iter()
+        .position(|arg| arg == "--replay")
+        .and_then(|i| args.get(i + 1));
+    if let Some(replay_path) = replay_path {
+        match Replay::read(replay_path.as_ref()) {
+            Ok(replay) => {
+                app.insert_resource(ReplayPlayback::new(replay));
+            }
+            Err(e) => {
+                eprintln!("{}: {}", replay_path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Steps the game one fixed step per frame no matter how long frames take, so runs can be reproduced
+    if replay_path.is_some() || args.iter().any(|arg| arg == "--deterministic") {
+        app.add_plugins(deterministic_plugin);
+    }
+
+    app.run();
+}
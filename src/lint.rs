@@ -0,0 +1,107 @@
+//! Checks level files for problems before they're merged, see [`validate_tmx`].
+//!
+//! ```text
+//! splittin-lint                                # every level in assets/levels
+//! splittin-lint assets/levels/level_1.tmx
+//! splittin-lint --assets my_assets my_assets/levels
+//! ```
+//!
+//! Prints one line per problem with the file and the Tiled object id, and exits with an error if
+//! there were any.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+pub use crate::in_game::levels::validate::{LevelProblem, validate_tmx, validate_tmx_file};
+
+const USAGE: &str = "Usage: splittin-lint [--assets <dir>] [<file or folder>...]";
+const DEFAULT_ASSETS_DIR: &str = "assets";
+const LEVELS_DIR: &str = "levels";
+
+/// Runs the linter with the command line arguments
+pub fn run() -> ExitCode {
+    let mut assets_dir = PathBuf::from(DEFAULT_ASSETS_DIR);
+    let mut paths = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--assets" => match args.next() {
+                Some(dir) => assets_dir = PathBuf::from(dir),
+                None => {
+                    eprintln!("--assets needs a value\n{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            },
+            _ if arg.starts_with("--") => {
+                eprintln!("Unknown argument {}\n{}", arg, USAGE);
+                return ExitCode::from(2);
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(assets_dir.join(LEVELS_DIR));
+    }
+
+    let mut files = Vec::new();
+    for path in &paths {
+        if let Err(e) = collect_levels(path, true, &mut files) {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::from(2);
+        }
+    }
+    files.sort();
+
+    let mut problem_count = 0;
+    let mut broken_levels = 0;
+    for file in &files {
+        let problems = match asset_path(&assets_dir, file) {
+            Some(asset_path) => validate_tmx_file(&assets_dir, &asset_path).unwrap_or_else(|e| {
+                vec![LevelProblem {
+                    object_id: None,
+                    message: e.to_string(),
+                }]
+            }),
+            None => vec![LevelProblem {
+                object_id: None,
+                message: format!("not inside the assets folder {}", assets_dir.display()),
+            }],
+        };
+        for problem in &problems {
+            println!("{}: {}", file.display(), problem);
+        }
+        problem_count += problems.len();
+        broken_levels += !problems.is_empty() as usize;
+    }
+
+    if problem_count > 0 {
+        eprintln!("{} problems in {} of {} levels", problem_count, broken_levels, files.len());
+        return ExitCode::FAILURE;
+    }
+    eprintln!("Checked {} levels, no problems", files.len());
+    ExitCode::SUCCESS
+}
+
+// Files given by name are checked whatever their extension, folders are searched for .tmx files
+fn collect_levels(path: &Path, given: bool, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if fs::metadata(path)?.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_levels(&entry?.path(), false, files)?;
+        }
+    } else if given || path.extension().is_some_and(|extension| extension == "tmx") {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+// Levels are parsed with their asset path, which external tilesets are resolved against
+fn asset_path(assets_dir: &Path, file: &Path) -> Option<String> {
+    let assets_dir = assets_dir.canonicalize().ok()?;
+    let file = file.canonicalize().ok()?;
+    let relative = file.strip_prefix(&assets_dir).ok()?;
+    // Asset paths use forward slashes on every platform
+    let components: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+    Some(components.join("/"))
+}
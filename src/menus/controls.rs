@@ -0,0 +1,242 @@
+use crate::in_game::bindings::{BindingConflict, BoundInput, InputBindings, RebindableAction};
+use crate::menus::input::{MenuInput, MenuInputContext};
+use crate::menus::main_menu::MenuScreen;
+use crate::menus::widgets::{
+    DIM_TEXT_COLOR, DefaultFocus, MenuButton, TEXT_COLOR, button_node, menu_button, menu_root, menu_text, menu_title,
+};
+use crate::menus::{MenuAction, MenuActivated, handle_menu_actions};
+use bevy::prelude::*;
+use bevy_enhanced_input::prelude::*;
+
+pub(super) fn controls_plugin(app: &mut App) {
+    app.init_resource::<Rebinding>()
+        .add_systems(OnEnter(MenuScreen::Controls), (reset_rebinding, spawn_controls).chain())
+        .add_systems(OnExit(MenuScreen::Controls), (reset_rebinding, toggle_menu_input).chain())
+        .add_systems(
+            Update,
+            (
+                handle_controls_actions,
+                capture_input,
+                toggle_menu_input.run_if(resource_changed::<Rebinding>),
+                update_control_labels.run_if(resource_changed::<InputBindings>),
+                update_status.run_if(resource_changed::<Rebinding>),
+            )
+                .chain()
+                // Clicks released while rebinding must be seen as ignored by the menu first
+                .after(handle_menu_actions)
+                .run_if(in_state(MenuScreen::Controls)),
+        );
+}
+
+const BACKGROUND: Color = Color::srgb(0.05, 0.05, 0.08);
+const CONTROL_BUTTON_WIDTH: f32 = 560.0;
+const CONTROL_BUTTON_HEIGHT: f32 = 48.0;
+const IDLE_MESSAGE: &str = "Pick an action to change its key or button";
+// How far a stick has to be pushed to count as pressed
+const STICK_THRESHOLD: f32 = 0.5;
+const STICK_AXES: [GamepadAxis; 4] = [
+    GamepadAxis::LeftStickX,
+    GamepadAxis::LeftStickY,
+    GamepadAxis::RightStickX,
+    GamepadAxis::RightStickY,
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum RebindPhase {
+    #[default]
+    Idle,
+    /// Waits for every key and button to be let go, so the one that started or ended rebinding isn't caught.
+    /// Listens for the next action afterwards, if there is one.
+    Releasing(Option<RebindableAction>),
+    Listening(RebindableAction),
+}
+
+/// Rebinding in progress on the controls screen. Menu input is off meanwhile.
+#[derive(Resource, Debug)]
+pub(super) struct Rebinding {
+    phase: RebindPhase,
+    message: String,
+}
+
+impl Default for Rebinding {
+    fn default() -> Self {
+        Self {
+            phase: RebindPhase::Idle,
+            message: IDLE_MESSAGE.to_string(),
+        }
+    }
+}
+
+impl Rebinding {
+    pub(super) fn is_active(&self) -> bool {
+        self.phase != RebindPhase::Idle
+    }
+}
+
+/// Label of a control button, listing the inputs of its action
+#[derive(Component)]
+struct ControlLabel(RebindableAction);
+
+impl ControlLabel {
+    fn text(&self, bindings: &InputBindings) -> String {
+        let inputs: Vec<_> = bindings.inputs(self.0).iter().map(BoundInput::name).collect();
+        format!("{}: {}", self.0.label(), inputs.join(", "))
+    }
+}
+
+#[derive(Component)]
+struct RebindStatus;
+
+fn spawn_controls(mut commands: Commands, bindings: Res<InputBindings>) {
+    commands
+        .spawn((menu_root("Controls", BACKGROUND), StateScoped(MenuScreen::Controls)))
+        .with_children(|parent| {
+            parent.spawn(menu_title("Controls"));
+            parent.spawn((menu_text(IDLE_MESSAGE, 22.0, DIM_TEXT_COLOR), RebindStatus));
+
+            for (i, action) in RebindableAction::ALL.into_iter().enumerate() {
+                let label = ControlLabel(action);
+                let mut button = parent.spawn((
+                    MenuButton(MenuAction::Rebind(action)),
+                    button_node(CONTROL_BUTTON_WIDTH, CONTROL_BUTTON_HEIGHT),
+                    BorderRadius::all(Val::Px(8.0)),
+                    children![(menu_text(label.text(&bindings), 24.0, TEXT_COLOR), label)],
+                ));
+                if i == 0 {
+                    button.insert(DefaultFocus);
+                }
+            }
+
+            parent.spawn(menu_button("Reset to defaults", MenuAction::ResetControls));
+            parent.spawn(menu_button("Back", MenuAction::BackToSettings));
+        });
+}
+
+fn reset_rebinding(mut rebinding: ResMut<Rebinding>) {
+    *rebinding = Rebinding::default();
+}
+
+fn handle_controls_actions(
+    mut activated_events: EventReader<MenuActivated>,
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<InputBindings>,
+) {
+    for MenuActivated(action) in activated_events.read() {
+        if rebinding.is_active() {
+            continue;
+        }
+        match action {
+            MenuAction::Rebind(action) => {
+                rebinding.phase = RebindPhase::Releasing(Some(*action));
+                rebinding.message = format!("Press a key or button for {}, Escape to cancel", action.label());
+            }
+            MenuAction::ResetControls => {
+                *bindings = InputBindings::default();
+                rebinding.message = "Controls reset to defaults".to_string();
+            }
+            _ => {}
+        }
+    }
+}
+
+fn capture_input(
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<InputBindings>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+) {
+    match rebinding.phase {
+        RebindPhase::Idle => {}
+        RebindPhase::Releasing(next) => {
+            let anything_pressed = keys.get_pressed().len() > 0
+                || mouse_buttons.get_pressed().len() > 0
+                || gamepads.iter().any(|gamepad| gamepad.get_pressed().next().is_some())
+                || gamepads.iter().any(|gamepad| pushed_stick(gamepad).is_some());
+            if !anything_pressed {
+                rebinding.phase = match next {
+                    Some(action) => RebindPhase::Listening(action),
+                    None => RebindPhase::Idle,
+                };
+            }
+        }
+        RebindPhase::Listening(action) => {
+            let cancel = keys.just_pressed(KeyCode::Escape)
+                || gamepads.iter().any(|gamepad| gamepad.just_pressed(GamepadButton::East));
+            if cancel {
+                rebinding.phase = RebindPhase::Releasing(None);
+                rebinding.message = IDLE_MESSAGE.to_string();
+                return;
+            }
+
+            let pressed = keys
+                .get_just_pressed()
+                .next()
+                .map(|key| BoundInput::Key(*key))
+                .or_else(|| mouse_buttons.get_just_pressed().next().map(|button| BoundInput::Mouse(*button)))
+                .or_else(|| {
+                    gamepads
+                        .iter()
+                        .find_map(|gamepad| gamepad.get_just_pressed().next().map(|button| BoundInput::Gamepad(*button)))
+                })
+                // Sticks are caught too, so pushing one is refused instead of doing nothing
+                .or_else(|| gamepads.iter().find_map(pushed_stick).map(BoundInput::Stick));
+            let Some(input) = pressed else {
+                return;
+            };
+
+            // A conflicting input is refused rather than moved over, so no action is ever left without input
+            rebinding.message = match bindings.conflict(action, input) {
+                Some(BindingConflict::Reserved(used_for)) => {
+                    format!("{} is used for {}, try another one", input.name(), used_for)
+                }
+                Some(BindingConflict::Action(other)) => {
+                    format!("{} is already used for {}, try another one", input.name(), other.label())
+                }
+                None => {
+                    bindings.rebind(action, input);
+                    info!("Bound {:?} to {:?}", action, input);
+                    format!("{} bound to {}", action.label(), input.name())
+                }
+            };
+            rebinding.phase = RebindPhase::Releasing(None);
+        }
+    }
+}
+
+fn pushed_stick(gamepad: &Gamepad) -> Option<GamepadAxis> {
+    STICK_AXES
+        .into_iter()
+        .find(|axis| gamepad.get(*axis).is_some_and(|value| value.abs() > STICK_THRESHOLD))
+}
+
+// Menu input is taken away while rebinding, otherwise the pressed key would also navigate or go back
+fn toggle_menu_input(
+    mut commands: Commands,
+    rebinding: Res<Rebinding>,
+    menu_inputs: Query<(Entity, Has<Actions<MenuInputContext>>), With<MenuInput>>,
+) {
+    for (entity, has_actions) in menu_inputs.iter() {
+        match (rebinding.is_active(), has_actions) {
+            (true, true) => {
+                commands.entity(entity).remove::<Actions<MenuInputContext>>();
+            }
+            (false, false) => {
+                commands.entity(entity).insert(Actions::<MenuInputContext>::default());
+            }
+            _ => {}
+        }
+    }
+}
+
+fn update_control_labels(bindings: Res<InputBindings>, mut labels: Query<(&mut Text, &ControlLabel)>) {
+    for (mut text, label) in labels.iter_mut() {
+        text.0 = label.text(&bindings);
+    }
+}
+
+fn update_status(rebinding: Res<Rebinding>, mut statuses: Query<&mut Text, With<RebindStatus>>) {
+    for mut text in statuses.iter_mut() {
+        text.0.clone_from(&rebinding.message);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::menus::MenuOpen;
+use bevy::prelude::*;
+use bevy_enhanced_input::prelude::*;
+
+pub(super) fn menu_input_plugin(app: &mut App) {
+    app.add_input_context::<MenuInputContext>()
+        .add_observer(binding)
+        .add_systems(OnEnter(MenuOpen), spawn_menu_input);
+}
+
+#[derive(Debug, InputAction)]
+#[input_action(output = Vec2)]
+pub(super) struct Navigate;
+
+#[derive(Debug, InputAction)]
+#[input_action(output = bool)]
+pub(super) struct Confirm;
+
+#[derive(Debug, InputAction)]
+#[input_action(output = bool)]
+pub(super) struct Back;
+
+#[derive(InputContext)]
+pub struct MenuInputContext;
+
+/// The entity holding the menu actions
+#[derive(Component)]
+pub(super) struct MenuInput;
+
+// The menu only listens to input while it's open, so menu keys don't leak into the game
+fn spawn_menu_input(mut commands: Commands) {
+    commands.spawn((
+        Name::new("Menu input"),
+        MenuInput,
+        Actions::<MenuInputContext>::default(),
+        StateScoped(MenuOpen),
+    ));
+}
+
+fn binding(trigger: Trigger<Binding<MenuInputContext>>, mut menus: Query<&mut Actions<MenuInputContext>>) {
+    let mut actions = menus.get_mut(trigger.target()).unwrap();
+    actions
+        .bind::<Navigate>()
+        .to((
+            Cardinal::arrow_keys(),
+            Cardinal::wasd_keys(),
+            Cardinal::dpad_buttons(),
+            Axial::left_stick(),
+        ))
+        .with_modifiers(DeadZone::default());
+
+    // Space isn't used here, it shoots as soon as the game resumes
+    actions
+        .bind::<Confirm>()
+        .to(KeyCode::Enter).to(KeyCode::NumpadEnter).to(GamepadButton::South);
+
+    actions
+        .bind::<Back>()
+        .to(KeyCode::Escape).to(KeyCode::Backspace).to(GamepadButton::East);
+}
@@ -0,0 +1,86 @@
+use crate::in_game::campaign::{Campaign, CampaignHandle, CampaignLevel, CampaignProgress};
+use crate::menus::MenuAction;
+use crate::menus::main_menu::MenuScreen;
+use crate::menus::widgets::{
+    DIM_TEXT_COLOR, DefaultFocus, Disabled, MenuButton, TEXT_COLOR, button_node, menu_button, menu_root, menu_text,
+    menu_title,
+};
+use bevy::prelude::*;
+
+pub(super) fn level_select_plugin(app: &mut App) {
+    app.add_systems(OnEnter(MenuScreen::LevelSelect), spawn_level_select);
+}
+
+const GRID_COLUMNS: usize = 4;
+const LEVEL_BUTTON_WIDTH: f32 = 220.0;
+const LEVEL_BUTTON_HEIGHT: f32 = 110.0;
+const GRID_GAP: f32 = 16.0;
+const BACKGROUND: Color = Color::srgb(0.05, 0.05, 0.08);
+
+fn spawn_level_select(
+    mut commands: Commands,
+    campaign_handle: Option<Res<CampaignHandle>>,
+    campaigns: Res<Assets<Campaign>>,
+    progress: Res<CampaignProgress>,
+) {
+    let campaign = campaign_handle.as_ref().and_then(|handle| campaigns.get(&handle.0));
+
+    commands
+        .spawn((menu_root("Level select", BACKGROUND), StateScoped(MenuScreen::LevelSelect)))
+        .with_children(|parent| {
+            parent.spawn(menu_title("Select level"));
+
+            let Some(campaign) = campaign else {
+                parent.spawn(menu_text("Loading levels...", 24.0, DIM_TEXT_COLOR));
+                parent.spawn((menu_button("Back", MenuAction::BackToMain), DefaultFocus));
+                return;
+            };
+            let continue_level = campaign.continue_level(&progress).map(|level| level.id.clone());
+
+            parent
+                .spawn(Node {
+                    width: Val::Px(GRID_COLUMNS as f32 * (LEVEL_BUTTON_WIDTH + GRID_GAP)),
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    column_gap: Val::Px(GRID_GAP),
+                    row_gap: Val::Px(GRID_GAP),
+                    ..default()
+                })
+                .with_children(|grid| {
+                    for (i, level) in campaign.levels.iter().enumerate() {
+                        let unlocked = campaign.is_unlocked(i, &progress);
+                        let mut button = grid.spawn((
+                            MenuButton(MenuAction::StartLevel(level.id.clone())),
+                            button_node(LEVEL_BUTTON_WIDTH, LEVEL_BUTTON_HEIGHT),
+                            BorderRadius::all(Val::Px(8.0)),
+                            children![
+                                menu_text(format!("{}. {}", i + 1, level.name), 22.0, TEXT_COLOR),
+                                menu_text(level_status(level, unlocked, &progress), 16.0, DIM_TEXT_COLOR),
+                            ],
+                        ));
+                        if !unlocked {
+                            button.insert(Disabled);
+                        }
+                        if continue_level.as_ref() == Some(&level.id) {
+                            button.insert(DefaultFocus);
+                        }
+                    }
+                });
+
+            parent.spawn(menu_button("Back", MenuAction::BackToMain));
+        });
+}
+
+fn level_status(level: &CampaignLevel, unlocked: bool, progress: &CampaignProgress) -> String {
+    if !unlocked {
+        return "Locked".to_string();
+    }
+    match progress.records.get(&level.id) {
+        Some(record) => format!("Best {}, {}/3 stars", record.best_score, record.stars),
+        None if progress.completed.contains(&level.id) => "Completed".to_string(),
+        None => match level.par_score {
+            Some(par_score) => format!("Par {}", par_score),
+            None => String::new(),
+        },
+    }
+}
@@ -0,0 +1,110 @@
+use crate::in_game::states::AppState;
+use crate::menus::MenuAction;
+use crate::menus::widgets::{DefaultFocus, TEXT_COLOR, labeled_button, menu_button, menu_root, menu_text, menu_title};
+use crate::settings::Settings;
+use bevy::prelude::*;
+
+pub(super) fn main_menu_plugin(app: &mut App) {
+    app.add_sub_state::<MenuScreen>()
+        .enable_state_scoped_entities::<MenuScreen>()
+        .add_systems(OnEnter(MenuScreen::Main), spawn_main_menu)
+        .add_systems(OnEnter(MenuScreen::Settings), spawn_settings)
+        .add_systems(
+            Update,
+            update_setting_labels.run_if(in_state(MenuScreen::Settings).and(resource_changed::<Settings>)),
+        );
+}
+
+/// Screens of the main menu
+#[derive(SubStates, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[source(AppState = AppState::MainMenu)]
+pub enum MenuScreen {
+    #[default]
+    Main,
+    LevelSelect,
+    Settings,
+    Controls,
+}
+
+const MENU_BACKGROUND: Color = Color::srgb(0.05, 0.05, 0.08);
+
+fn spawn_main_menu(mut commands: Commands) {
+    commands.spawn((
+        menu_root("Main menu", MENU_BACKGROUND),
+        StateScoped(MenuScreen::Main),
+        children![
+            menu_title("Splittin"),
+            (menu_button("Play", MenuAction::Play), DefaultFocus),
+            menu_button("Level select", MenuAction::LevelSelect),
+            menu_button("Settings", MenuAction::Settings),
+            menu_button("Quit", MenuAction::Quit),
+        ],
+    ));
+}
+
+/// Label of a settings button, showing the current value of the setting
+#[derive(Component)]
+enum SettingLabel {
+    AimPreview,
+    ShootMode,
+    ForceKeys,
+}
+
+impl SettingLabel {
+    fn text(&self, settings: &Settings) -> String {
+        match self {
+            Self::AimPreview => format!("Aim preview: {}", on_off(settings.aim_preview)),
+            Self::ShootMode => format!("Shooting: {}", settings.shoot_mode.label()),
+            Self::ForceKeys => {
+                let mode = if settings.ramp_force_keys { "Hold to ramp" } else { "Step per press" };
+                format!("Force keys: {}", mode)
+            }
+        }
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value { "On" } else { "Off" }
+}
+
+fn spawn_settings(mut commands: Commands, settings: Res<Settings>) {
+    commands.spawn((
+        menu_root("Settings", MENU_BACKGROUND),
+        StateScoped(MenuScreen::Settings),
+        children![
+            menu_title("Settings"),
+            (
+                labeled_button(
+                    (
+                        menu_text(SettingLabel::AimPreview.text(&settings), 28.0, TEXT_COLOR),
+                        SettingLabel::AimPreview,
+                    ),
+                    MenuAction::ToggleAimPreview,
+                ),
+                DefaultFocus,
+            ),
+            labeled_button(
+                (
+                    menu_text(SettingLabel::ShootMode.text(&settings), 28.0, TEXT_COLOR),
+                    SettingLabel::ShootMode,
+                ),
+                MenuAction::CycleShootMode,
+            ),
+            labeled_button(
+                (
+                    menu_text(SettingLabel::ForceKeys.text(&settings), 28.0, TEXT_COLOR),
+                    SettingLabel::ForceKeys,
+                ),
+                MenuAction::ToggleRampForceKeys,
+            ),
+            menu_button("Controls", MenuAction::Controls),
+            menu_button("Back", MenuAction::BackToMain),
+        ],
+    ));
+}
+
+fn update_setting_labels(settings: Res<Settings>, mut labels: Query<(&mut Text, &SettingLabel)>) {
+    for (mut text, label) in labels.iter_mut() {
+        text.0 = label.text(&settings);
+    }
+}
@@ -0,0 +1,144 @@
+mod controls;
+mod input;
+mod level_select;
+mod main_menu;
+mod overlays;
+mod widgets;
+
+use crate::in_game::bindings::RebindableAction;
+use crate::in_game::campaign::{Campaign, CampaignHandle, CampaignProgress, StartLevel};
+use crate::in_game::states::{AppState, GameState};
+use crate::menus::controls::Rebinding;
+use crate::menus::input::Back;
+use crate::menus::main_menu::MenuScreen;
+use crate::settings::Settings;
+use bevy::prelude::*;
+use bevy_enhanced_input::events::Started;
+
+pub(crate) fn menus_plugin(app: &mut App) {
+    app.add_computed_state::<MenuOpen>()
+        .enable_state_scoped_entities::<MenuOpen>()
+        .add_event::<MenuActivated>()
+        .add_plugins((
+            input::menu_input_plugin,
+            widgets::widgets_plugin,
+            main_menu::main_menu_plugin,
+            level_select::level_select_plugin,
+            controls::controls_plugin,
+            overlays::overlays_plugin,
+        ))
+        .add_observer(go_back)
+        .add_systems(Update, handle_menu_actions.run_if(in_state(MenuOpen)));
+}
+
+/// Exists while any menu is on screen, in the main menu or over a running level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MenuOpen;
+
+impl ComputedStates for MenuOpen {
+    type SourceStates = (AppState, Option<GameState>);
+
+    fn compute((app_state, game_state): (AppState, Option<GameState>)) -> Option<Self> {
+        match (app_state, game_state) {
+            (AppState::MainMenu, _) => Some(MenuOpen),
+            (_, Some(GameState::Paused | GameState::LevelComplete | GameState::GameOver)) => Some(MenuOpen),
+            _ => None,
+        }
+    }
+}
+
+/// What a menu button does
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuAction {
+    /// Continue the campaign with the first level that isn't completed
+    Play,
+    LevelSelect,
+    Settings,
+    Quit,
+    BackToMain,
+    StartLevel(String),
+    ToggleAimPreview,
+    CycleShootMode,
+    ToggleRampForceKeys,
+    Controls,
+    Rebind(RebindableAction),
+    ResetControls,
+    BackToSettings,
+    Resume,
+    Restart,
+    QuitToMenu,
+}
+
+/// Sent when a menu button is clicked or confirmed
+#[derive(Event, Clone, Debug)]
+pub struct MenuActivated(pub MenuAction);
+
+// Back leaves the sub screens of the main menu, going up one level. The pause overlay is closed by the pause action itself,
+// which is bound to the same keys.
+fn go_back(
+    _trigger: Trigger<Started<Back>>,
+    menu_screen: Option<Res<State<MenuScreen>>>,
+    mut activated: EventWriter<MenuActivated>,
+) {
+    match menu_screen.map(|screen| *screen.get()) {
+        Some(MenuScreen::LevelSelect | MenuScreen::Settings) => {
+            activated.write(MenuActivated(MenuAction::BackToMain));
+        }
+        Some(MenuScreen::Controls) => {
+            activated.write(MenuActivated(MenuAction::BackToSettings));
+        }
+        _ => {}
+    }
+}
+
+fn handle_menu_actions(
+    mut activated_events: EventReader<MenuActivated>,
+    rebinding: Res<Rebinding>,
+    campaign_handle: Option<Res<CampaignHandle>>,
+    campaigns: Res<Assets<Campaign>>,
+    progress: Res<CampaignProgress>,
+    mut settings: ResMut<Settings>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut menu_screen: ResMut<NextState<MenuScreen>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut start_level: EventWriter<StartLevel>,
+    mut app_exit: EventWriter<AppExit>,
+) {
+    for MenuActivated(action) in activated_events.read() {
+        // Clicks while picking a new input only pick the input
+        if rebinding.is_active() {
+            continue;
+        }
+        match action {
+            MenuAction::Play => {
+                let campaign = campaign_handle.as_ref().and_then(|handle| campaigns.get(&handle.0));
+                let Some(level) = campaign.and_then(|campaign| campaign.continue_level(&progress)) else {
+                    warn!("Campaign isn't loaded or has no levels");
+                    continue;
+                };
+                start_level.write(StartLevel { id: level.id.clone() });
+                app_state.set(AppState::InGame);
+            }
+            MenuAction::LevelSelect => menu_screen.set(MenuScreen::LevelSelect),
+            MenuAction::Settings => menu_screen.set(MenuScreen::Settings),
+            MenuAction::Quit => {
+                app_exit.write(AppExit::Success);
+            }
+            MenuAction::BackToMain => menu_screen.set(MenuScreen::Main),
+            MenuAction::StartLevel(id) => {
+                start_level.write(StartLevel { id: id.clone() });
+                app_state.set(AppState::InGame);
+            }
+            MenuAction::ToggleAimPreview => settings.aim_preview = !settings.aim_preview,
+            MenuAction::CycleShootMode => settings.shoot_mode = settings.shoot_mode.next(),
+            MenuAction::ToggleRampForceKeys => settings.ramp_force_keys = !settings.ramp_force_keys,
+            MenuAction::Controls => menu_screen.set(MenuScreen::Controls),
+            // Handled by the controls screen
+            MenuAction::Rebind(_) | MenuAction::ResetControls => {}
+            MenuAction::BackToSettings => menu_screen.set(MenuScreen::Settings),
+            MenuAction::Resume => game_state.set(GameState::Playing),
+            MenuAction::Restart => game_state.set(GameState::Loading),
+            MenuAction::QuitToMenu => app_state.set(AppState::MainMenu),
+        }
+    }
+}
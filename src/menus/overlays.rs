@@ -0,0 +1,78 @@
+use crate::in_game::campaign::{Campaign, CampaignHandle};
+use crate::in_game::levels::CurrentLevel;
+use crate::in_game::scoring::Score;
+use crate::in_game::states::GameState;
+use crate::menus::MenuAction;
+use crate::menus::widgets::{DefaultFocus, TEXT_COLOR, menu_button, menu_root, menu_text, menu_title};
+use bevy::prelude::*;
+
+pub(super) fn overlays_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameState::Paused), spawn_pause_overlay)
+        .add_systems(OnEnter(GameState::GameOver), spawn_game_over_overlay)
+        .add_systems(OnEnter(GameState::LevelComplete), spawn_level_complete_overlay);
+}
+
+// The level stays visible behind the overlays
+const OVERLAY_BACKGROUND: Color = Color::srgba(0.0, 0.0, 0.0, 0.6);
+
+fn spawn_pause_overlay(mut commands: Commands) {
+    commands.spawn((
+        menu_root("Pause overlay", OVERLAY_BACKGROUND),
+        StateScoped(GameState::Paused),
+        children![
+            menu_title("Paused"),
+            (menu_button("Resume", MenuAction::Resume), DefaultFocus),
+            menu_button("Restart", MenuAction::Restart),
+            menu_button("Quit to menu", MenuAction::QuitToMenu),
+        ],
+    ));
+}
+
+fn spawn_game_over_overlay(mut commands: Commands) {
+    commands.spawn((
+        menu_root("Game over overlay", OVERLAY_BACKGROUND),
+        StateScoped(GameState::GameOver),
+        children![
+            menu_title("Out of shots"),
+            (menu_button("Retry", MenuAction::Restart), DefaultFocus),
+            menu_button("Quit to menu", MenuAction::QuitToMenu),
+        ],
+    ));
+}
+
+fn spawn_level_complete_overlay(
+    mut commands: Commands,
+    score: Res<Score>,
+    current_level: Option<Res<CurrentLevel>>,
+    campaign_handle: Option<Res<CampaignHandle>>,
+    campaigns: Res<Assets<Campaign>>,
+) {
+    let next_level = campaign_handle
+        .as_ref()
+        .and_then(|handle| campaigns.get(&handle.0))
+        .zip(current_level.as_ref())
+        .and_then(|(campaign, current_level)| campaign.next_level(&current_level.id))
+        .map(|level| level.id.clone());
+    let title = if next_level.is_some() { "Level complete" } else { "Campaign complete" };
+
+    commands
+        .spawn((
+            menu_root("Level complete overlay", OVERLAY_BACKGROUND),
+            StateScoped(GameState::LevelComplete),
+        ))
+        .with_children(|parent| {
+            parent.spawn(menu_title(title));
+            parent.spawn(menu_text(format!("Score {}", score.total), 32.0, TEXT_COLOR));
+            match next_level {
+                // The campaign moves on by itself after a moment, this just skips the wait
+                Some(next_level) => {
+                    parent.spawn((menu_button("Next level", MenuAction::StartLevel(next_level)), DefaultFocus));
+                    parent.spawn(menu_button("Retry", MenuAction::Restart));
+                }
+                None => {
+                    parent.spawn((menu_button("Retry", MenuAction::Restart), DefaultFocus));
+                }
+            }
+            parent.spawn(menu_button("Quit to menu", MenuAction::QuitToMenu));
+        });
+}
@@ -0,0 +1,211 @@
+use crate::menus::input::{Confirm, Navigate};
+use crate::menus::{MenuAction, MenuActivated, MenuOpen};
+use bevy::prelude::*;
+use bevy_enhanced_input::events::Started;
+
+pub(super) fn widgets_plugin(app: &mut App) {
+    app.init_resource::<MenuFocus>()
+        .add_observer(navigate)
+        .add_observer(confirm)
+        .add_systems(
+            Update,
+            (click_buttons, keep_focus, style_buttons)
+                .chain()
+                .run_if(in_state(MenuOpen)),
+        );
+}
+
+#[derive(Component)]
+#[require(Button)]
+pub struct MenuButton(pub MenuAction);
+
+/// A button that is shown but can't be used, like a locked level
+#[derive(Component)]
+pub struct Disabled;
+
+/// The button that gets focus when its screen opens
+#[derive(Component)]
+pub struct DefaultFocus;
+
+/// The button keyboard and gamepad input acts on
+#[derive(Resource, Default)]
+pub struct MenuFocus(pub Option<Entity>);
+
+const BUTTON_COLOR: Color = Color::srgb(0.15, 0.15, 0.2);
+const FOCUSED_BUTTON_COLOR: Color = Color::srgb(0.3, 0.35, 0.5);
+const DISABLED_BUTTON_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
+const FOCUSED_BORDER_COLOR: Color = Color::srgb(1.0, 0.9, 0.3);
+pub const TEXT_COLOR: Color = Color::srgb(0.95, 0.95, 0.95);
+pub const DIM_TEXT_COLOR: Color = Color::srgb(0.6, 0.6, 0.6);
+
+/// Full screen column that centers a menu, drawn over everything else
+pub fn menu_root(name: &'static str, background: Color) -> impl Bundle {
+    (
+        Name::new(name),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            row_gap: Val::Px(16.0),
+            ..default()
+        },
+        BackgroundColor(background),
+        GlobalZIndex(10),
+    )
+}
+
+pub fn menu_text(text: impl Into<String>, font_size: f32, color: Color) -> impl Bundle {
+    (
+        Text::new(text),
+        TextFont {
+            font_size,
+            ..default()
+        },
+        TextColor(color),
+    )
+}
+
+pub fn menu_title(text: impl Into<String>) -> impl Bundle {
+    menu_text(text, 56.0, TEXT_COLOR)
+}
+
+pub fn button_node(width: f32, height: f32) -> Node {
+    Node {
+        width: Val::Px(width),
+        height: Val::Px(height),
+        flex_direction: FlexDirection::Column,
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        border: UiRect::all(Val::Px(3.0)),
+        ..default()
+    }
+}
+
+pub fn menu_button(label: impl Into<String>, action: MenuAction) -> impl Bundle {
+    labeled_button(menu_text(label, 28.0, TEXT_COLOR), action)
+}
+
+/// A menu button with a custom label entity, for labels that change
+pub fn labeled_button(label: impl Bundle, action: MenuAction) -> impl Bundle {
+    (
+        MenuButton(action),
+        button_node(360.0, 56.0),
+        BorderRadius::all(Val::Px(8.0)),
+        children![label],
+    )
+}
+
+fn click_buttons(
+    buttons: Query<(Entity, &Interaction, &MenuButton, Has<Disabled>), Changed<Interaction>>,
+    mut focus: ResMut<MenuFocus>,
+    mut pressed: Local<Option<Entity>>,
+    mut activated: EventWriter<MenuActivated>,
+) {
+    for (entity, interaction, button, disabled) in buttons.iter() {
+        match interaction {
+            Interaction::Pressed => *pressed = Some(entity),
+            Interaction::Hovered => {
+                // Buttons act when the mouse button is released over them,
+                // otherwise the held button would shoot right away when the game resumes
+                if pressed.take() == Some(entity) && !disabled {
+                    activated.write(MenuActivated(button.0.clone()));
+                }
+                if focus.0 != Some(entity) {
+                    focus.0 = Some(entity);
+                }
+            }
+            Interaction::None => {
+                if *pressed == Some(entity) {
+                    *pressed = None;
+                }
+            }
+        }
+    }
+}
+
+// Focus moves to the default button whenever the focused one goes away, like when the screen changes
+fn keep_focus(
+    mut focus: ResMut<MenuFocus>,
+    buttons: Query<Entity, With<MenuButton>>,
+    default_focus: Query<Entity, (With<MenuButton>, With<DefaultFocus>)>,
+) {
+    if focus.0.is_some_and(|entity| buttons.contains(entity)) {
+        return;
+    }
+
+    let new_focus = default_focus.iter().next().or_else(|| buttons.iter().next());
+    if focus.0 != new_focus {
+        focus.0 = new_focus;
+    }
+}
+
+fn style_buttons(
+    focus: Res<MenuFocus>,
+    mut buttons: Query<(Entity, &mut BackgroundColor, &mut BorderColor, Has<Disabled>), With<MenuButton>>,
+) {
+    for (entity, mut background, mut border, disabled) in buttons.iter_mut() {
+        let focused = focus.0 == Some(entity);
+        let background_color = match (disabled, focused) {
+            (true, _) => DISABLED_BUTTON_COLOR,
+            (false, true) => FOCUSED_BUTTON_COLOR,
+            (false, false) => BUTTON_COLOR,
+        };
+        let border_color = if focused { FOCUSED_BORDER_COLOR } else { background_color };
+        background.set_if_neq(BackgroundColor(background_color));
+        border.set_if_neq(BorderColor(border_color));
+    }
+}
+
+// Moves focus to the closest button in the pushed direction, which works for lists and grids alike
+fn navigate(
+    trigger: Trigger<Started<Navigate>>,
+    mut focus: ResMut<MenuFocus>,
+    buttons: Query<(Entity, &GlobalTransform), With<MenuButton>>,
+) {
+    // UI coordinates grow downwards
+    let input = Vec2::new(trigger.value.x, -trigger.value.y);
+    let direction = if input.x.abs() > input.y.abs() {
+        Vec2::new(input.x.signum(), 0.0)
+    } else if input.y != 0.0 {
+        Vec2::new(0.0, input.y.signum())
+    } else {
+        return;
+    };
+
+    let Some(current) = focus.0.and_then(|entity| buttons.get(entity).ok()) else {
+        return;
+    };
+    let current_position = current.1.translation().truncate();
+
+    let closest = buttons
+        .iter()
+        .filter_map(|(entity, transform)| {
+            let offset = transform.translation().truncate() - current_position;
+            let along = offset.dot(direction);
+            if along <= 0.0 {
+                return None;
+            }
+            // Prefer buttons in line with the current one over closer ones off to the side
+            let across = offset.perp_dot(direction).abs();
+            Some((entity, along + across * 2.0))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+
+    if let Some((entity, _)) = closest {
+        focus.0 = Some(entity);
+    }
+}
+
+fn confirm(
+    _trigger: Trigger<Started<Confirm>>,
+    focus: Res<MenuFocus>,
+    buttons: Query<(&MenuButton, Has<Disabled>)>,
+    mut activated: EventWriter<MenuActivated>,
+) {
+    if let Some(Ok((button, false))) = focus.0.map(|entity| buttons.get(entity)) {
+        activated.write(MenuActivated(button.0.clone()));
+    }
+}
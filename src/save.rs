@@ -0,0 +1,442 @@
+//! Campaign progress and settings, saved as RON in the platform data directory
+//! (`~/.local/share/splittin/save.ron` on Linux). Controls live next to it in `controls.ron`,
+//! so they can be shared or reset on their own.
+//!
+//! The file starts with a `version`. Adding a field with a serde default keeps old saves readable,
+//! anything else bumps [`SAVE_VERSION`] and teaches [`migrate`] to read the previous version.
+
+use crate::in_game::bindings::InputBindings;
+use crate::in_game::campaign::{CampaignProgress, LevelRecord};
+use crate::settings::{Settings, ShootMode};
+use bevy::asset::ron;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub(crate) fn save_plugin(app: &mut App) {
+    app.add_systems(PreStartup, load_save)
+        .add_systems(Last, (write_save, write_controls));
+}
+
+pub const SAVE_VERSION: u32 = 1;
+pub const CONTROLS_VERSION: u32 = 1;
+const SAVE_DIR_NAME: &str = "splittin";
+const SAVE_FILE_NAME: &str = "save.ron";
+const CONTROLS_FILE_NAME: &str = "controls.ron";
+
+/// Where the save files live. Writing a file is off if there is no place for it, or if the file
+/// there can't be read safely and must not be overwritten.
+#[derive(Resource, Debug)]
+pub struct SaveLocation {
+    pub path: Option<PathBuf>,
+    pub controls_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ControlsFile {
+    version: u32,
+    bindings: InputBindings,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SaveFile {
+    version: u32,
+    #[serde(default)]
+    progress: SavedProgress,
+    #[serde(default)]
+    settings: SavedSettings,
+}
+
+// BTree collections keep the file in a stable order, so saves diff nicely
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedProgress {
+    #[serde(default)]
+    completed: BTreeSet<String>,
+    #[serde(default)]
+    unlocked: BTreeSet<String>,
+    #[serde(default)]
+    records: BTreeMap<String, SavedRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedRecord {
+    best_score: u32,
+    fewest_shots: u32,
+    #[serde(default)]
+    stars: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct SavedSettings {
+    aim_preview: bool,
+    shoot_mode: ShootMode,
+    ramp_force_keys: bool,
+}
+
+impl Default for SavedSettings {
+    fn default() -> Self {
+        Self::from(&Settings::default())
+    }
+}
+
+impl From<&Settings> for SavedSettings {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            aim_preview: settings.aim_preview,
+            shoot_mode: settings.shoot_mode,
+            ramp_force_keys: settings.ramp_force_keys,
+        }
+    }
+}
+
+impl SavedSettings {
+    fn apply(self, settings: &mut Settings) {
+        settings.aim_preview = self.aim_preview;
+        settings.shoot_mode = self.shoot_mode;
+        settings.ramp_force_keys = self.ramp_force_keys;
+    }
+}
+
+impl From<&CampaignProgress> for SavedProgress {
+    fn from(progress: &CampaignProgress) -> Self {
+        Self {
+            completed: progress.completed.iter().cloned().collect(),
+            unlocked: progress.unlocked.iter().cloned().collect(),
+            records: progress
+                .records
+                .iter()
+                .map(|(id, record)| {
+                    (
+                        id.clone(),
+                        SavedRecord {
+                            best_score: record.best_score,
+                            fewest_shots: record.fewest_shots,
+                            stars: record.stars,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<SavedProgress> for CampaignProgress {
+    fn from(saved: SavedProgress) -> Self {
+        Self {
+            completed: saved.completed.into_iter().collect(),
+            unlocked: saved.unlocked.into_iter().collect(),
+            records: saved
+                .records
+                .into_iter()
+                .map(|(id, record)| {
+                    (
+                        id,
+                        LevelRecord {
+                            best_score: record.best_score,
+                            fewest_shots: record.fewest_shots,
+                            stars: record.stars,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    /// Written by a newer build of the game
+    NewerVersion(u32),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Save file IO error: {}", e),
+            Self::Parse(e) => write!(f, "Failed to parse save file: {}", e),
+            Self::Serialize(e) => write!(f, "Failed to serialize save file: {}", e),
+            Self::NewerVersion(version) => write!(
+                f,
+                "Save file version {} is newer than the supported version {}",
+                version, SAVE_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+/// The directory of the save files, in the platform data directory if the platform has one
+pub fn save_dir() -> Option<PathBuf> {
+    Some(dirs::data_dir()?.join(SAVE_DIR_NAME))
+}
+
+/// Just the version of a save file, read before the rest to know how to read it
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+fn parse_save(text: &str) -> Result<SaveFile, SaveError> {
+    let header: SaveHeader = ron::from_str(text).map_err(SaveError::Parse)?;
+    migrate(header.version, text)
+}
+
+fn parse_controls(text: &str) -> Result<ControlsFile, SaveError> {
+    let header: SaveHeader = ron::from_str(text).map_err(SaveError::Parse)?;
+    if header.version > CONTROLS_VERSION {
+        return Err(SaveError::NewerVersion(header.version));
+    }
+    ron::from_str(text).map_err(SaveError::Parse)
+}
+
+// A missing file is a fresh start
+fn read_file(path: &Path) -> Result<Option<String>, SaveError> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(SaveError::Io(e)),
+    }
+}
+
+/// Reads a save of any known version as the current version
+fn migrate(version: u32, text: &str) -> Result<SaveFile, SaveError> {
+    match version {
+        version if version > SAVE_VERSION => Err(SaveError::NewerVersion(version)),
+        // Version 1 is the first one there is. Once the format changes, the old layout gets its own
+        // struct here, and is read with it and converted to the current one.
+        _ => ron::from_str(text).map_err(SaveError::Parse),
+    }
+}
+
+/// Progress and settings read from a save file
+pub struct LoadedSave {
+    pub progress: CampaignProgress,
+    pub settings: Settings,
+}
+
+pub fn read_save(path: &Path) -> Result<Option<LoadedSave>, SaveError> {
+    let Some(text) = read_file(path)? else {
+        return Ok(None);
+    };
+    let save = parse_save(&text)?;
+
+    let mut settings = Settings::default();
+    save.settings.apply(&mut settings);
+    Ok(Some(LoadedSave {
+        progress: save.progress.into(),
+        settings,
+    }))
+}
+
+pub fn read_controls(path: &Path) -> Result<Option<InputBindings>, SaveError> {
+    let Some(text) = read_file(path)? else {
+        return Ok(None);
+    };
+    Ok(Some(parse_controls(&text)?.bindings.with_missing_defaults()))
+}
+
+pub fn write_save_file(path: &Path, progress: &CampaignProgress, settings: &Settings) -> Result<(), SaveError> {
+    write_ron(
+        path,
+        &SaveFile {
+            version: SAVE_VERSION,
+            progress: progress.into(),
+            settings: settings.into(),
+        },
+    )
+}
+
+pub fn write_controls_file(path: &Path, bindings: &InputBindings) -> Result<(), SaveError> {
+    write_ron(
+        path,
+        &ControlsFile {
+            version: CONTROLS_VERSION,
+            bindings: bindings.clone(),
+        },
+    )
+}
+
+// Writes through a temporary file, so a crash mid write never leaves a broken file behind
+fn write_ron(path: &Path, value: &impl Serialize) -> Result<(), SaveError> {
+    let text = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()).map_err(SaveError::Serialize)?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(SaveError::Io)?;
+    }
+    let temp_path = path.with_extension("ron.tmp");
+    let mut file = fs::File::create(&temp_path).map_err(SaveError::Io)?;
+    file.write_all(text.as_bytes()).map_err(SaveError::Io)?;
+    file.sync_all().map_err(SaveError::Io)?;
+    fs::rename(&temp_path, path).map_err(SaveError::Io)
+}
+
+/// Reads a file with `read`. Returns what was read, and the path if the file may be written to.
+fn load_file<T>(
+    path: PathBuf,
+    read: impl FnOnce(&Path) -> Result<Option<T>, SaveError>,
+) -> (Option<T>, Option<PathBuf>) {
+    match read(&path) {
+        Ok(Some(loaded)) => {
+            info!("Loaded {}", path.display());
+            (Some(loaded), Some(path))
+        }
+        Ok(None) => (None, Some(path)),
+        Err(SaveError::NewerVersion(version)) => {
+            // Keep the newer file intact for the build that wrote it
+            error!(
+                "{} has version {}, which is newer than this build, it won't be saved",
+                path.display(),
+                version
+            );
+            (None, None)
+        }
+        Err(e) => {
+            // Keep a copy of the broken file around, then start over
+            error!("{}: {}, starting over", path.display(), e);
+            let backup_path = path.with_extension("ron.broken");
+            match fs::rename(&path, &backup_path) {
+                Ok(()) => {
+                    warn!("Moved the broken file to {}", backup_path.display());
+                    (None, Some(path))
+                }
+                Err(e) => {
+                    error!("Failed to back up the broken file: {}", e);
+                    (None, None)
+                }
+            }
+        }
+    }
+}
+
+fn load_save(
+    mut commands: Commands,
+    mut progress: ResMut<CampaignProgress>,
+    mut settings: ResMut<Settings>,
+    mut bindings: ResMut<InputBindings>,
+) {
+    let Some(dir) = save_dir() else {
+        warn!("No data directory found, progress won't be saved");
+        commands.insert_resource(SaveLocation {
+            path: None,
+            controls_path: None,
+        });
+        return;
+    };
+
+    let (save, path) = load_file(dir.join(SAVE_FILE_NAME), read_save);
+    if let Some(save) = save {
+        *progress = save.progress;
+        *settings = save.settings;
+    }
+
+    let (controls, controls_path) = load_file(dir.join(CONTROLS_FILE_NAME), read_controls);
+    if let Some(controls) = controls {
+        *bindings = controls;
+    }
+
+    commands.insert_resource(SaveLocation { path, controls_path });
+}
+
+fn write_save(
+    location: Option<Res<SaveLocation>>,
+    progress: Res<CampaignProgress>,
+    settings: Res<Settings>,
+    mut has_run: Local<bool>,
+) {
+    // Everything looks changed on the first run, but that's only the save that was just loaded
+    let changed = progress.is_changed() || settings.is_changed();
+    if !std::mem::replace(&mut *has_run, true) || !changed {
+        return;
+    }
+    let Some(path) = location.as_ref().and_then(|location| location.path.as_ref()) else {
+        return;
+    };
+
+    match write_save_file(path, &progress, &settings) {
+        Ok(()) => debug!("Saved progress to {}", path.display()),
+        Err(e) => error!("Failed to save progress: {}", e),
+    }
+}
+
+fn write_controls(location: Option<Res<SaveLocation>>, bindings: Res<InputBindings>, mut has_run: Local<bool>) {
+    // Same as the save, the first run only sees the controls that were just loaded
+    if !std::mem::replace(&mut *has_run, true) || !bindings.is_changed() {
+        return;
+    }
+    let Some(path) = location.as_ref().and_then(|location| location.controls_path.as_ref()) else {
+        return;
+    };
+
+    match write_controls_file(path, &bindings) {
+        Ok(()) => debug!("Saved controls to {}", path.display()),
+        Err(e) => error!("Failed to save controls: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_save_round_trips() {
+        let mut settings = Settings::default();
+        settings.aim_preview = !settings.aim_preview;
+        let save = SaveFile {
+            version: SAVE_VERSION,
+            progress: SavedProgress {
+                completed: BTreeSet::from(["level_1".to_string()]),
+                ..default()
+            },
+            settings: SavedSettings::from(&settings),
+        };
+        let text = ron::to_string(&save).unwrap();
+
+        let read = parse_save(&text).unwrap();
+        assert_eq!(read.progress.completed, save.progress.completed);
+        assert_eq!(read.settings.aim_preview, settings.aim_preview);
+    }
+
+    #[test]
+    fn newer_save_is_rejected() {
+        let text = format!("(version: {})", SAVE_VERSION + 1);
+        assert!(matches!(parse_save(&text), Err(SaveError::NewerVersion(_))));
+    }
+
+    #[test]
+    fn save_is_only_written_after_a_change() {
+        let path = std::env::temp_dir()
+            .join(format!("splittin-test-{}", std::process::id()))
+            .join("save.ron");
+        let _ = fs::remove_file(&path);
+
+        let mut app = App::new();
+        app.insert_resource(SaveLocation {
+            path: Some(path.clone()),
+            controls_path: None,
+        })
+        .init_resource::<CampaignProgress>()
+        .init_resource::<Settings>()
+        .add_systems(Last, write_save);
+
+        app.update();
+        app.update();
+        assert!(!path.exists(), "the loaded save was written back");
+
+        app.world_mut()
+            .resource_mut::<CampaignProgress>()
+            .completed
+            .insert("level_1".to_string());
+        app.update();
+        let written = parse_save(&fs::read_to_string(&path).unwrap());
+        let _ = fs::remove_file(&path);
+        assert!(written.unwrap().progress.completed.contains("level_1"));
+    }
+}
@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub(crate) fn settings_plugin(app: &mut App) {
+    app.init_resource::<Settings>();
+}
+
+/// Player preferences that apply to every level
+#[derive(Resource, Debug, Clone)]
+pub struct Settings {
+    /// Draw the predicted shot path in levels that allow it
+    pub aim_preview: bool,
+    /// How shots get their force, unless the level picks one
+    pub shoot_mode: ShootMode,
+    /// Holding the force keys ramps the force smoothly, instead of stepping it once per press
+    pub ramp_force_keys: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            aim_preview: true,
+            shoot_mode: ShootMode::default(),
+            ramp_force_keys: false,
+        }
+    }
+}
+
+/// How the player sets the force of a shot
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShootMode {
+    /// The force keys step the force up and down, shooting fires right away
+    #[default]
+    Stepped,
+    /// Holding shoot fills the force up, releasing fires
+    Charge,
+    /// Like charge, but the force swings between its minimum and maximum while held
+    Swing,
+}
+
+impl ShootMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Stepped => "Stepped",
+            Self::Charge => "Hold to charge",
+            Self::Swing => "Hold to swing",
+        }
+    }
+
+    /// The mode after this one, for cycling through them in the settings
+    pub fn next(self) -> Self {
+        match self {
+            Self::Stepped => Self::Charge,
+            Self::Charge => Self::Swing,
+            Self::Swing => Self::Stepped,
+        }
+    }
+
+    /// Reads the `shoot_mode` level property
+    pub fn from_property(value: &str) -> Option<Self> {
+        match value {
+            "stepped" => Some(Self::Stepped),
+            "charge" => Some(Self::Charge),
+            "swing" => Some(Self::Swing),
+            _ => None,
+        }
+    }
+
+    /// Whether holding shoot charges the shot, instead of shooting right away
+    pub fn charges(&self) -> bool {
+        *self != Self::Stepped
+    }
+}
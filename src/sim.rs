@@ -0,0 +1,349 @@
+//! Headless simulation of levels, for checking in scripts that levels can still be solved.
+//!
+//! Plays shots on a level without a window, audio or input, as fast as the machine allows, and
+//! reports what they did. The shots come from a replay file or from the command line:
+//!
+//! ```text
+//! splittin-sim --replay replays/level_1.last.ron
+//! splittin-sim --level level_1 --shot 0:60:40000 --shot 300:120:25000
+//! ```
+//!
+//! `--shot TICK:ANGLE:FORCE` fires on the given fixed step of the level, at an angle in degrees
+//! counterclockwise from the right, with a force between 5,000 and 100,000. The results are printed
+//! to stdout as JSON.
+
+use crate::in_game::balls::level_ball::LevelBall;
+use crate::in_game::gameplay_plugin;
+use crate::in_game::levels::{CurrentLevel, LevelLoadFailed};
+use crate::in_game::outcome::LevelProgress;
+use crate::in_game::player::gun_angle;
+use crate::in_game::replay::REPLAY_VERSION;
+use crate::in_game::scoring::{Score, ScoringSystems, ShotScored};
+use crate::in_game::simulation::{GameplaySystems, LevelSeed, LevelTick, SIMULATION_HZ, deterministic_plugin};
+use crate::in_game::states::InLevel;
+use crate::settings::settings_plugin;
+use avian2d::PhysicsPlugins;
+use bevy::asset::AssetPlugin;
+use bevy::log::{Level, LogPlugin};
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use serde::Serialize;
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// Simulations are described with replays, for callers outside the game too
+pub use crate::in_game::replay::{Replay, ReplayError, ReplayPlayback, ReplayShot};
+
+const USAGE: &str = "Usage: splittin-sim (--replay <file> | --level <id> --shot TICK:ANGLE:FORCE...) [--max-ticks <ticks>]";
+// Two minutes of play, plenty for any shot to settle
+pub(crate) const DEFAULT_MAX_TICKS: u64 = 120 * SIMULATION_HZ as u64;
+// Loading the campaign and the level happens on other threads, give up if it never finishes
+const LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The logger is global, only the first simulation of a process can set it up
+static LOGGER_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// Runs the simulator with the command line arguments
+pub fn run() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (playback, max_ticks) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match simulate(playback, max_ticks) {
+        Ok(report) => {
+            println!("{}", serde_json::to_string_pretty(&report).expect("the report is plain data"));
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<(ReplayPlayback, u64), String> {
+    let mut replay_path = None;
+    let mut level_id = None;
+    let mut shots = Vec::new();
+    let mut max_ticks = DEFAULT_MAX_TICKS;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--replay" => replay_path = Some(value()?.clone()),
+            "--level" => level_id = Some(value()?.clone()),
+            "--shot" => shots.push(parse_shot(value()?)?),
+            "--max-ticks" => {
+                let ticks = value()?;
+                max_ticks = ticks.parse().map_err(|_| format!("Invalid tick count {}", ticks))?;
+            }
+            other => return Err(format!("Unknown argument {}", other)),
+        }
+    }
+
+    let playback = match (replay_path, level_id) {
+        (Some(path), None) if shots.is_empty() => {
+            ReplayPlayback::new(Replay::read(Path::new(&path)).map_err(|e| format!("{}: {}", path, e))?)
+        }
+        (None, Some(level_id)) => {
+            // Shots fired on the same step stay in the order they were given
+            shots.sort_by_key(|shot: &ReplayShot| shot.tick);
+            ReplayPlayback::with_level_seed(Replay {
+                version: REPLAY_VERSION,
+                level_id,
+                seed: 0,
+                shots,
+            })
+        }
+        _ => return Err("Give either a replay or a level with shots".to_string()),
+    };
+    Ok((playback, max_ticks))
+}
+
+fn parse_shot(value: &str) -> Result<ReplayShot, String> {
+    let invalid = || format!("Invalid shot {}, expected TICK:ANGLE:FORCE", value);
+    let [tick, angle, force] = value.split(':').collect::<Vec<_>>()[..] else {
+        return Err(invalid());
+    };
+    let angle: f32 = angle.parse().map_err(|_| invalid())?;
+    Ok(ReplayShot {
+        tick: tick.parse().map_err(|_| invalid())?,
+        player: 0,
+        position: None,
+        angle: gun_angle(angle.to_radians()),
+        force: force.parse().map_err(|_| invalid())?,
+    })
+}
+
+#[derive(Debug)]
+pub enum SimError {
+    /// The campaign has no level with this id
+    UnknownLevel(String),
+    /// The level file couldn't be loaded
+    LevelLoadFailed(String),
+    /// The campaign or the level didn't load in time, the log says why
+    LoadTimeout,
+}
+
+impl std::fmt::Display for SimError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownLevel(id) => write!(f, "Unknown level {}", id),
+            Self::LevelLoadFailed(e) => write!(f, "Failed to load the level: {}", e),
+            Self::LoadTimeout => write!(f, "Timed out loading the level"),
+        }
+    }
+}
+
+impl std::error::Error for SimError {}
+
+/// What the shots of a simulation did
+#[derive(Debug, Clone, Serialize)]
+pub struct SimReport {
+    pub level: String,
+    pub seed: u64,
+    /// The goal of the level was reached
+    pub cleared: bool,
+    /// The level ran out of ammo without reaching its goal
+    pub failed: bool,
+    /// The tick limit was hit before the shots settled
+    pub timed_out: bool,
+    pub ticks: u64,
+    pub score: u32,
+    pub best_shot: u32,
+    /// Longest chain of pops of a single shot
+    pub best_chain: u32,
+    pub balls_popped: u32,
+    pub balls_remaining: u32,
+    pub shots_fired: u32,
+    /// Every settled shot, in the order they were fired
+    pub shots: Vec<ShotReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ShotReport {
+    /// Length of the chain reaction of the shot
+    pub pops: u32,
+    pub points: u32,
+    pub multi_split_bonus: u32,
+    pub multiplier: f32,
+}
+
+/// Shots scored so far, by ammo id so they can be put back in firing order
+#[derive(Resource, Default)]
+struct ScoredShots(Vec<(u32, ShotReport)>);
+
+/// Plays the shots of a replay on its level until they settled or the level is over
+pub fn simulate(playback: ReplayPlayback, max_ticks: u64) -> Result<SimReport, SimError> {
+    let level_id = playback.replay.level_id.clone();
+    let mut app = simulation_app(playback);
+    app.finish();
+    app.cleanup();
+
+    let started = Instant::now();
+    loop {
+        app.update();
+        let world = app.world();
+
+        let Some(playback) = world.get_resource::<ReplayPlayback>() else {
+            // Removed again when the campaign doesn't have the level
+            return Err(SimError::UnknownLevel(level_id));
+        };
+        if world.get_resource::<State<InLevel>>().is_none() {
+            // Nothing retries a failed load, waiting for the timeout would only waste time
+            if let Some(failed) = world.get_resource::<LevelLoadFailed>() {
+                return Err(SimError::LevelLoadFailed(failed.0.clone()));
+            }
+            if started.elapsed() > LOAD_TIMEOUT {
+                return Err(SimError::LoadTimeout);
+            }
+            continue;
+        }
+
+        let progress = world.resource::<LevelProgress>();
+        let tick = world.resource::<LevelTick>().0;
+        // Shots keep settling after the level is over, their points still count
+        let shots_done = progress.finished || (playback.finished() && tick > playback.last_shot_tick());
+        if shots_done && progress.all_shots_settled() {
+            return Ok(report(app.world_mut(), false));
+        }
+        if tick >= max_ticks {
+            return Ok(report(app.world_mut(), true));
+        }
+    }
+}
+
+fn simulation_app(playback: ReplayPlayback) -> App {
+    let mut app = App::new();
+    if !LOGGER_INSTALLED.swap(true, Ordering::Relaxed) {
+        app.add_plugins(LogPlugin {
+            level: Level::WARN,
+            // Tileset images can't be loaded without a renderer, which is fine, nothing draws them
+            filter: "bevy_asset=off".to_string(),
+            ..default()
+        });
+    }
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            watch_for_changes_override: Some(false),
+            ..default()
+        },
+        TransformPlugin,
+        StatesPlugin,
+    ));
+    // Levels and physics refer to these, even though nothing renders them
+    app.init_asset::<Image>()
+        .init_asset::<TextureAtlasLayout>()
+        .init_asset::<Mesh>()
+        .add_plugins(PhysicsPlugins::default())
+        .add_plugins((settings_plugin, gameplay_plugin, deterministic_plugin))
+        .init_resource::<ScoredShots>()
+        .add_systems(
+            FixedUpdate,
+            collect_scored_shots.in_set(GameplaySystems::Track).after(ScoringSystems),
+        )
+        .insert_resource(playback);
+    app
+}
+
+fn collect_scored_shots(mut scored_events: EventReader<ShotScored>, mut scored_shots: ResMut<ScoredShots>) {
+    for scored in scored_events.read() {
+        scored_shots.0.push((
+            scored.ammo_id,
+            ShotReport {
+                pops: scored.pops,
+                points: scored.points,
+                multi_split_bonus: scored.multi_split_bonus,
+                multiplier: scored.multiplier,
+            },
+        ));
+    }
+}
+
+fn report(world: &mut World, timed_out: bool) -> SimReport {
+    let balls_remaining = world.query_filtered::<(), With<LevelBall>>().iter(world).count() as u32;
+    let mut scored_shots = std::mem::take(&mut world.resource_mut::<ScoredShots>().0);
+    scored_shots.sort_by_key(|(ammo_id, _)| *ammo_id);
+
+    let progress = world.resource::<LevelProgress>();
+    let score = world.resource::<Score>();
+    SimReport {
+        level: world.resource::<CurrentLevel>().id.clone(),
+        seed: world.resource::<LevelSeed>().0,
+        // The game state only follows on the next update, after the loop already stopped
+        cleared: progress.cleared(),
+        failed: progress.failed(),
+        timed_out,
+        ticks: world.resource::<LevelTick>().0,
+        score: score.total,
+        best_shot: score.best_shot,
+        best_chain: score.best_chain,
+        balls_popped: progress.total_pops,
+        balls_remaining,
+        shots_fired: progress.shots_fired,
+        shots: scored_shots.into_iter().map(|(_, shot)| shot).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn shot_from_tick_angle_and_force() {
+        let shot = parse_shot("30:90:40000").unwrap();
+        assert_eq!(shot.tick, 30);
+        assert_eq!(shot.angle, gun_angle(90.0_f32.to_radians()));
+        assert_eq!(shot.force, 40_000.0);
+        assert_eq!(shot.position, None);
+    }
+
+    #[test]
+    fn invalid_shots() {
+        for shot in ["", "30:90", "30:90:40000:1", "-1:90:40000", "30:up:40000", "30:90:"] {
+            assert!(parse_shot(shot).is_err(), "{} was accepted", shot);
+        }
+    }
+
+    #[test]
+    fn level_shots_are_sorted_by_tick() {
+        let (playback, max_ticks) =
+            parse_args(&args(&["--level", "level_1", "--shot", "300:0:5000", "--shot", "0:45:5000"])).unwrap();
+        assert_eq!(playback.replay.level_id, "level_1");
+        let ticks: Vec<u64> = playback.replay.shots.iter().map(|shot| shot.tick).collect();
+        assert_eq!(ticks, [0, 300]);
+        assert_eq!(max_ticks, DEFAULT_MAX_TICKS);
+    }
+
+    #[test]
+    fn max_ticks_argument() {
+        let (_, max_ticks) = parse_args(&args(&["--level", "level_1", "--max-ticks", "600"])).unwrap();
+        assert_eq!(max_ticks, 600);
+    }
+
+    #[test]
+    fn replay_and_shots_are_exclusive() {
+        assert!(parse_args(&args(&[])).is_err());
+        assert!(parse_args(&args(&["--replay", "a.ron", "--level", "level_1"])).is_err());
+        assert!(parse_args(&args(&["--replay", "a.ron", "--shot", "0:0:5000"])).is_err());
+    }
+
+    #[test]
+    fn unknown_and_incomplete_arguments() {
+        assert!(parse_args(&args(&["--level"])).is_err());
+        assert!(parse_args(&args(&["--level", "level_1", "--fast"])).is_err());
+    }
+}
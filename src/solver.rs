@@ -0,0 +1,423 @@
+//! Searches a level for good shots by simulating them headlessly, see [`crate::sim`].
+//!
+//! Single shots are sampled over the whole circle of angles and the force range of the player.
+//! Sequences are built from the best of them one shot at a time, each next shot fired once the
+//! ones before settled, until a sequence clears the level or the shot limit is reached.
+//!
+//! ```text
+//! splittin-solve --level level_1 --by score --max-shots 3 --save level_1.solution.ron
+//! ```
+//!
+//! Angles are in degrees counterclockwise from the right, like the shots of `splittin-sim`.
+
+use crate::in_game::player::{ShootingForce, gun_angle};
+use crate::in_game::replay::REPLAY_VERSION;
+use crate::sim::{self, Replay, ReplayPlayback, ReplayShot, SimError, SimReport};
+use serde::Serialize;
+use std::num::NonZero;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::thread;
+
+const USAGE: &str = "Usage: splittin-solve --level <id> [--by pops|score] [--angles <n>] [--forces <n>] \
+                     [--max-shots <n>] [--beam <n>] [--save <file>]";
+// Single shots listed in the report
+const BEST_SHOTS_SHOWN: usize = 10;
+
+/// What makes a shot better than another, after clearing the level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SolveMetric {
+    Pops,
+    Score,
+}
+
+impl SolveMetric {
+    fn value(&self, report: &SimReport) -> u32 {
+        match self {
+            Self::Pops => report.balls_popped,
+            Self::Score => report.score,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SolveOptions {
+    pub level_id: String,
+    pub metric: SolveMetric,
+    /// Angles tried per shot, spread evenly around the circle
+    pub angles: u32,
+    /// Forces tried per angle, spread evenly from the minimum to the maximum force
+    pub forces: u32,
+    /// Most shots in a sequence
+    pub max_shots: u32,
+    /// Sequences kept to build on after each shot
+    pub beam_width: usize,
+    pub max_ticks: u64,
+}
+
+impl Default for SolveOptions {
+    fn default() -> Self {
+        Self {
+            level_id: String::new(),
+            metric: SolveMetric::Pops,
+            angles: 36,
+            forces: 8,
+            max_shots: 3,
+            beam_width: 3,
+            max_ticks: sim::DEFAULT_MAX_TICKS,
+        }
+    }
+}
+
+/// A sampled shot
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SolvedShot {
+    pub tick: u64,
+    /// Degrees counterclockwise from the right
+    pub angle: f32,
+    pub force: f32,
+}
+
+impl SolvedShot {
+    fn replay_shot(&self) -> ReplayShot {
+        ReplayShot {
+            tick: self.tick,
+            player: 0,
+            position: None,
+            angle: gun_angle(self.angle.to_radians()),
+            force: self.force,
+        }
+    }
+}
+
+/// Shots and what they did when simulated
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    pub shots: Vec<SolvedShot>,
+    pub result: SimReport,
+}
+
+impl Candidate {
+    // Clearing the level comes first, then the metric, then needing fewer shots
+    fn rank(&self, metric: SolveMetric) -> (bool, u32, std::cmp::Reverse<usize>) {
+        (
+            self.result.cleared,
+            metric.value(&self.result),
+            std::cmp::Reverse(self.shots.len()),
+        )
+    }
+
+    /// Whether another shot can be fired after these
+    fn can_continue(&self) -> bool {
+        !self.result.cleared && !self.result.failed && !self.result.timed_out
+    }
+
+    /// The shots as a replay that can be watched in the game
+    pub fn replay(&self) -> Replay {
+        Replay {
+            version: REPLAY_VERSION,
+            level_id: self.result.level.clone(),
+            seed: self.result.seed,
+            shots: self.shots.iter().map(SolvedShot::replay_shot).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Solution {
+    pub level: String,
+    pub ranked_by: SolveMetric,
+    pub simulations: usize,
+    /// The best single shots, best first
+    pub best_shots: Vec<Candidate>,
+    /// The best sequence found, the one that clears the level with the fewest shots if any does
+    pub best_sequence: Option<Candidate>,
+}
+
+/// Runs the solver with the command line arguments
+pub fn run() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (options, save_path) = match parse_args(&args) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let solution = match solve(&options) {
+        Ok(solution) => solution,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("{}", serde_json::to_string_pretty(&solution).expect("the solution is plain data"));
+
+    if let Some(path) = save_path {
+        let Some(best) = &solution.best_sequence else {
+            eprintln!("No shot found to save");
+            return ExitCode::FAILURE;
+        };
+        if let Err(e) = best.replay().write(&path) {
+            eprintln!("{}: {}", path.display(), e);
+            return ExitCode::FAILURE;
+        }
+        eprintln!("Saved the best sequence to {}", path.display());
+    }
+    ExitCode::SUCCESS
+}
+
+fn parse_args(args: &[String]) -> Result<(SolveOptions, Option<PathBuf>), String> {
+    let mut options = SolveOptions::default();
+    let mut level_id = None;
+    let mut save_path = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+        let invalid = || format!("Invalid value {} for {}", value, arg);
+        match arg.as_str() {
+            "--level" => level_id = Some(value.clone()),
+            "--by" => {
+                options.metric = match value.as_str() {
+                    "pops" => SolveMetric::Pops,
+                    "score" => SolveMetric::Score,
+                    _ => return Err(invalid()),
+                }
+            }
+            "--angles" => options.angles = value.parse().map_err(|_| invalid())?,
+            "--forces" => options.forces = value.parse().map_err(|_| invalid())?,
+            "--max-shots" => options.max_shots = value.parse().map_err(|_| invalid())?,
+            "--beam" => options.beam_width = value.parse().map_err(|_| invalid())?,
+            "--max-ticks" => options.max_ticks = value.parse().map_err(|_| invalid())?,
+            "--save" => save_path = Some(PathBuf::from(value)),
+            other => return Err(format!("Unknown argument {}", other)),
+        }
+    }
+
+    options.level_id = level_id.ok_or("Which level? Give it with --level")?;
+    if options.angles == 0 || options.forces == 0 || options.max_shots == 0 || options.beam_width == 0 {
+        return Err("Angles, forces, shots and beam width must be at least 1".to_string());
+    }
+    Ok((options, save_path))
+}
+
+/// Searches the level for the best single shots and the best sequence of shots
+pub fn solve(options: &SolveOptions) -> Result<Solution, SimError> {
+    let aims = sample_aims(options);
+    let mut simulations = 0;
+
+    let singles: Vec<_> = aims.iter().map(|&(angle, force)| vec![SolvedShot { tick: 0, angle, force }]).collect();
+    eprintln!("Trying {} single shots", singles.len());
+    let mut candidates = simulate_all(options, singles)?;
+    simulations += candidates.len();
+    sort_best_first(&mut candidates, options.metric);
+
+    let best_shots = candidates.iter().take(BEST_SHOTS_SHOWN).cloned().collect();
+    let mut best_sequence = candidates.first().cloned();
+
+    let mut beam = candidates;
+    for shot_count in 2..=options.max_shots {
+        // Fewer shots beat more, once the level is cleared there's nothing left to look for
+        if best_sequence.as_ref().is_some_and(|best| best.result.cleared) {
+            break;
+        }
+        beam.retain(Candidate::can_continue);
+        beam.truncate(options.beam_width);
+        if beam.is_empty() {
+            break;
+        }
+
+        let sequences: Vec<_> = beam
+            .iter()
+            .flat_map(|candidate| {
+                // The next shot goes once the ones before it settled
+                let tick = candidate.result.ticks;
+                aims.iter().map(move |&(angle, force)| {
+                    let mut shots = candidate.shots.clone();
+                    shots.push(SolvedShot { tick, angle, force });
+                    shots
+                })
+            })
+            .collect();
+        eprintln!("Trying {} sequences of {} shots", sequences.len(), shot_count);
+        beam = simulate_all(options, sequences)?;
+        simulations += beam.len();
+        sort_best_first(&mut beam, options.metric);
+
+        let Some(candidate) = beam.first() else {
+            break;
+        };
+        if best_sequence
+            .as_ref()
+            .is_none_or(|best| candidate.rank(options.metric) > best.rank(options.metric))
+        {
+            best_sequence = Some(candidate.clone());
+        }
+    }
+
+    Ok(Solution {
+        level: options.level_id.clone(),
+        ranked_by: options.metric,
+        simulations,
+        best_shots,
+        best_sequence,
+    })
+}
+
+// Every angle and force combination to try
+fn sample_aims(options: &SolveOptions) -> Vec<(f32, f32)> {
+    let force = ShootingForce::default();
+    let force_step = if options.forces > 1 {
+        (force.max() - force.min()) / (options.forces - 1) as f32
+    } else {
+        0.0
+    };
+    let angle_step = 360.0 / options.angles as f32;
+
+    (0..options.angles)
+        .flat_map(|i| (0..options.forces).map(move |j| (i as f32 * angle_step, force.min() + j as f32 * force_step)))
+        .collect()
+}
+
+fn sort_best_first(candidates: &mut [Candidate], metric: SolveMetric) {
+    candidates.sort_by(|a, b| b.rank(metric).cmp(&a.rank(metric)));
+}
+
+// Simulations share nothing, so they're spread over all cores
+fn simulate_all(options: &SolveOptions, sequences: Vec<Vec<SolvedShot>>) -> Result<Vec<Candidate>, SimError> {
+    let threads = thread::available_parallelism().map_or(1, NonZero::get);
+    let chunk_size = sequences.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = sequences
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(|shots| simulate(options, shots)).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("a simulation panicked"))
+            .collect()
+    })
+}
+
+fn simulate(options: &SolveOptions, shots: &[SolvedShot]) -> Result<Candidate, SimError> {
+    let playback = ReplayPlayback::with_level_seed(Replay {
+        version: REPLAY_VERSION,
+        level_id: options.level_id.clone(),
+        seed: 0,
+        shots: shots.iter().map(SolvedShot::replay_shot).collect(),
+    });
+    Ok(Candidate {
+        shots: shots.to_vec(),
+        result: sim::simulate(playback, options.max_ticks)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn level_with_defaults() {
+        let (options, save_path) = parse_args(&args(&["--level", "level_1"])).unwrap();
+        assert_eq!(options.level_id, "level_1");
+        assert_eq!(options.metric, SolveMetric::Pops);
+        assert_eq!(options.max_shots, SolveOptions::default().max_shots);
+        assert_eq!(save_path, None);
+    }
+
+    #[test]
+    fn every_option() {
+        let (options, save_path) = parse_args(&args(&[
+            "--level", "level_2", "--by", "score", "--angles", "72", "--forces", "4", "--max-shots", "2", "--beam",
+            "5", "--max-ticks", "900", "--save", "best.ron",
+        ]))
+        .unwrap();
+        assert_eq!(options.metric, SolveMetric::Score);
+        assert_eq!((options.angles, options.forces, options.max_shots), (72, 4, 2));
+        assert_eq!((options.beam_width, options.max_ticks), (5, 900));
+        assert_eq!(save_path, Some(PathBuf::from("best.ron")));
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        assert!(parse_args(&args(&[])).is_err());
+        assert!(parse_args(&args(&["--level", "level_1", "--by", "speed"])).is_err());
+        assert!(parse_args(&args(&["--level", "level_1", "--angles", "0"])).is_err());
+        assert!(parse_args(&args(&["--level", "level_1", "--beam"])).is_err());
+        assert!(parse_args(&args(&["--level", "level_1", "--fast", "1"])).is_err());
+    }
+
+    #[test]
+    fn aims_cover_the_circle_and_the_force_range() {
+        let options = SolveOptions {
+            angles: 4,
+            forces: 2,
+            ..SolveOptions::default()
+        };
+        let force = ShootingForce::default();
+        let aims = sample_aims(&options);
+        assert_eq!(aims.len(), 8);
+        assert_eq!(aims[0], (0.0, force.min()));
+        assert_eq!(aims[1].0, 0.0);
+        assert!((aims[1].1 - force.max()).abs() < 1.0);
+        assert_eq!(aims[7].0, 270.0);
+    }
+
+    fn candidate(cleared: bool, failed: bool, timed_out: bool) -> Candidate {
+        Candidate {
+            shots: vec![SolvedShot {
+                tick: 0,
+                angle: 90.0,
+                force: 40_000.0,
+            }],
+            result: SimReport {
+                level: "level_1".to_string(),
+                seed: 0,
+                cleared,
+                failed,
+                timed_out,
+                ticks: 300,
+                score: 0,
+                best_shot: 0,
+                best_chain: 0,
+                balls_popped: 0,
+                balls_remaining: 1,
+                shots_fired: 1,
+                shots: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn unfinished_candidate_continues() {
+        assert!(candidate(false, false, false).can_continue());
+    }
+
+    #[test]
+    fn failed_candidate_is_pruned() {
+        assert!(!candidate(false, true, false).can_continue());
+    }
+
+    #[test]
+    fn cleared_or_timed_out_candidate_is_pruned() {
+        assert!(!candidate(true, false, false).can_continue());
+        assert!(!candidate(false, false, true).can_continue());
+    }
+
+    #[test]
+    fn clearing_ranks_above_the_metric() {
+        let mut cleared = candidate(true, false, false);
+        cleared.result.balls_popped = 1;
+        let mut failed = candidate(false, true, false);
+        failed.result.balls_popped = 10;
+        assert!(cleared.rank(SolveMetric::Pops) > failed.rank(SolveMetric::Pops));
+    }
+}